@@ -0,0 +1,65 @@
+// Compares sequential and tiled generation of large mazes. Run with
+// `cargo bench --bench generation`.
+
+use std::time::{Duration, Instant};
+
+use maze::batch;
+use maze::maze::Maze;
+use maze::tiled::TileOptions;
+
+const SIZES: [usize; 3] = [250, 500, 1000];
+const RUNS: usize = 3;
+
+// Shortest time taken by `build` over a few runs
+fn time<F: FnMut() -> Maze>(mut build: F) -> (Duration, Maze) {
+    let mut best = None;
+    let mut maze = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        maze = Some(build());
+        let elapsed = start.elapsed();
+        best = Some(best.map_or(elapsed, |b: Duration| b.min(elapsed)));
+    }
+    (best.unwrap(), maze.unwrap())
+}
+
+// Share of cells with 1 to 4 open sides, which shows how closely the tiled
+// maze follows the wall weights
+fn get_degrees(maze: &Maze) -> [f64; 4] {
+    let mut counts = [0; 4];
+    for row in 0..maze.height {
+        for col in 0..maze.width {
+            let degree = maze.neighbors(row, col).len();
+            counts[degree.clamp(1, 4) - 1] += 1;
+        }
+    }
+    counts.map(|count| count as f64 / (maze.width * maze.height) as f64)
+}
+
+fn main() {
+    let config = batch::get_preset("ramp").unwrap();
+    println!(
+        "{:>6} {:>12} {:>12} {:>8}  degrees 1/2/3/4 (sequential | tiled)",
+        "size", "sequential", "tiled", "speedup"
+    );
+    for size in SIZES {
+        let (sequential, sequential_maze) =
+            time(|| Maze::build(size, size, config.clone()).unwrap());
+        let options = TileOptions {
+            tile_size: 64,
+            ..Default::default()
+        };
+        let (tiled, tiled_maze) =
+            time(|| Maze::build_tiled(size, size, config.clone(), &options).unwrap());
+        let format = |degrees: [f64; 4]| degrees.map(|share| format!("{:.3}", share)).join("/");
+        println!(
+            "{:>6} {:>12.2?} {:>12.2?} {:>7.2}x  {} | {}",
+            size,
+            sequential,
+            tiled,
+            sequential.as_secs_f64() / tiled.as_secs_f64(),
+            format(get_degrees(&sequential_maze)),
+            format(get_degrees(&tiled_maze)),
+        );
+    }
+}
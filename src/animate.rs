@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use crate::image::Rgb;
+use crate::maze::{GenerationEvent, Maze};
+
+// Settings for `Maze::to_animated_svg`
+#[derive(Debug, Clone)]
+pub struct AnimationOptions {
+    // Pixels across every cell
+    pub cell_size: usize,
+    // Pixels across every wall
+    pub wall_size: usize,
+    // Seconds spent on every wall drawn by the generator
+    pub frame_time: f64,
+    pub wall_color: Rgb,
+    pub background: Rgb,
+    // Colour flashed by a wall when it is drawn and removed
+    pub considered_color: Rgb,
+    // Colour flashed by a wall when it is drawn but kept to avoid a loop
+    pub rejected_color: Rgb,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            cell_size: 16,
+            wall_size: 4,
+            frame_time: 0.05,
+            wall_color: [0, 0, 0],
+            background: [255, 255, 255],
+            considered_color: [33, 113, 181],
+            rejected_color: [203, 24, 29],
+        }
+    }
+}
+
+// What happens to a wall at some point of the animation
+#[derive(Clone, Copy)]
+enum Change {
+    Flash(Rgb),
+    Hide,
+}
+
+impl Maze {
+    // Draw the generation of the maze as an animated SVG, starting with
+    // every wall in place and replaying `events`, as reported by
+    // `Maze::build_with_events` for this maze, one frame per wall drawn.
+    // Forced openings and walls removed around crossings get a frame each.
+    pub fn to_animated_svg(
+        &self,
+        events: &[GenerationEvent],
+        options: &AnimationOptions,
+    ) -> String {
+        let mut changes: HashMap<(usize, usize), Vec<(f64, Change)>> = HashMap::new();
+        let mut frame = 0;
+        let mut considered = None;
+        for &event in events {
+            let (edge, change) = match event {
+                GenerationEvent::EdgeConsidered { edge, .. } => {
+                    (edge, Change::Flash(options.considered_color))
+                }
+                GenerationEvent::EdgeRemoved { edge } => (edge, Change::Hide),
+                GenerationEvent::EdgeRejectedCycle { edge } => {
+                    (edge, Change::Flash(options.rejected_color))
+                }
+                GenerationEvent::LoopsCreated { .. } => continue,
+            };
+            // The outcome of a wall shows in the frame it was drawn in
+            if considered.take() != Some(edge) {
+                frame += 1;
+            }
+            if let GenerationEvent::EdgeConsidered { .. } = event {
+                considered = Some(edge);
+            }
+            let time = frame as f64 * options.frame_time;
+            for position in self.get_wall_positions(edge).unwrap_or_default() {
+                changes.entry(position).or_default().push((time, change));
+            }
+        }
+
+        let size = |k: usize| {
+            if k % 2 == 1 {
+                options.cell_size
+            } else {
+                options.wall_size
+            }
+        };
+        let offset = |k: usize| k.div_ceil(2) * options.wall_size + k / 2 * options.cell_size;
+        let rows = self.grid.len();
+        let cols = self.width * 2 + 1;
+
+        let mut res = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            offset(cols),
+            offset(rows)
+        );
+        res.push_str(&format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            get_color(options.background)
+        ));
+        for (i, row) in self.grid.iter().enumerate() {
+            for (j, &wall) in row.iter().enumerate() {
+                let changes = changes.get(&(i, j));
+                if !wall && changes.is_none() {
+                    continue;
+                }
+                let rect = format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"",
+                    offset(j),
+                    offset(i),
+                    size(j),
+                    size(i),
+                    get_color(options.wall_color)
+                );
+                let Some(changes) = changes else {
+                    res.push_str(&rect);
+                    res.push_str("/>\n");
+                    continue;
+                };
+                res.push_str(&rect);
+                res.push_str(">\n");
+                for (time, change) in changes {
+                    match change {
+                        Change::Flash(color) => res.push_str(&format!(
+                            "  <set attributeName=\"fill\" to=\"{}\" begin=\"{:.3}s\" dur=\"{:.3}s\"/>\n",
+                            get_color(*color),
+                            time,
+                            options.frame_time
+                        )),
+                        Change::Hide => res.push_str(&format!(
+                            "  <set attributeName=\"visibility\" to=\"hidden\" begin=\"{:.3}s\" fill=\"freeze\"/>\n",
+                            time + options.frame_time
+                        )),
+                    }
+                }
+                res.push_str("</rect>\n");
+            }
+        }
+        res.push_str("</svg>\n");
+        res
+    }
+}
+
+fn get_color([r, g, b]: Rgb) -> String {
+    format!("rgb({},{},{})", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::maze::{BuildOptions, WallWeights};
+
+    #[test]
+    fn every_wall_drawn_gets_a_frame() {
+        let mut events = Vec::new();
+        let options = BuildOptions {
+            seed: Some(5),
+            ..Default::default()
+        };
+        let maze = Maze::build_with_events(6, 4, WallWeights::from_fn(|_| 1), options, |event| {
+            events.push(event)
+        })
+        .unwrap();
+        let considered = events
+            .iter()
+            .filter(|event| matches!(event, GenerationEvent::EdgeConsidered { .. }))
+            .count();
+        let removed = events
+            .iter()
+            .filter(|event| matches!(event, GenerationEvent::EdgeRemoved { .. }))
+            .count();
+
+        let options = AnimationOptions::default();
+        let svg = maze.to_animated_svg(&events, &options);
+        // 7 walls and 6 cells across, 5 walls and 4 cells down
+        assert!(svg.contains("width=\"124\" height=\"84\""));
+        let frames: HashSet<&str> = svg
+            .lines()
+            .filter(|line| line.contains("attributeName=\"fill\""))
+            .filter_map(|line| line.split("begin=\"").nth(1))
+            .collect();
+        assert_eq!(frames.len(), considered);
+        assert_eq!(svg.matches("to=\"hidden\"").count(), removed);
+        assert_eq!(removed, 6 * 4 - 1);
+    }
+}
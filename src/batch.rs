@@ -0,0 +1,419 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::thread;
+
+use crate::difficulty::Metrics;
+use crate::maze::{Axis, Boundary, BuildError, BuildOptions, Maze, WallType, WallWeights};
+
+// Names of the weights returned by `get_preset`
+pub const PRESETS: [&str; 2] = ["uniform", "ramp"];
+
+// Mazes handed to the threads at a time, per thread
+const CHUNK_SIZE: usize = 64;
+
+// A parameter sweep. Every combination of size, preset and seed gives one
+// maze, and the maze only depends on that combination and `options`, never on
+// the number of threads.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    // Width and height of the mazes
+    pub sizes: Vec<(usize, usize)>,
+    // Named wall weights
+    pub presets: Vec<(String, WallWeights)>,
+    pub seeds: Vec<u64>,
+    // Settings shared by every maze, whose seed is replaced by the seed of
+    // each maze
+    pub options: BuildOptions,
+    // Threads generating mazes, 0 for one per core
+    pub threads: usize,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Sweep {
+            sizes: vec![(20, 20)],
+            presets: vec![("uniform".to_string(), get_preset("uniform").unwrap())],
+            seeds: (0..100).collect(),
+            options: BuildOptions::default(),
+            threads: 0,
+        }
+    }
+}
+
+// A generated maze with the parameters it was generated from
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub seed: u64,
+    // Index into `Sweep::presets`
+    pub preset: usize,
+    pub maze: Maze,
+    // Shortest path from the top left cell to the bottom right cell, `None`
+    // if either is excluded
+    pub metrics: Option<Metrics>,
+}
+
+// How records are written by `export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // One JSON object per line. The maze is stored in the `print` layout,
+    // which `Maze::from_ascii_with_options` reads back with
+    // `ParseOptions::infer_topology`, although a joined border that no
+    // passage crosses reads back as closed. `topology` always tells them
+    // apart.
+    JsonLines,
+    // A header listing the presets, followed by one record per maze, all in
+    // little endian:
+    //
+    // header: b"MAZB", u16 version (1), u16 preset count, then per preset a
+    //         u16 name length, the UTF-8 name and 24 u32 weights in the order
+    //         of `WallType::ALL`
+    // record: u32 width, u32 height, u64 seed, u16 preset, u8 horizontal and
+    //         u8 vertical boundary (0 closed, 1 wrapped, 2 twisted), u32 path
+    //         length, u32 turns, u32 dead ends (all u32::MAX without a path),
+    //         u32 crossing count, per crossing u32 row, u32 col and u8 axis on
+    //         top (0 horizontal, 1 vertical), then the `grid` row by row, one
+    //         bit per position with the first in the highest bit, padded to
+    //         a whole byte
+    Binary,
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    Build(BuildError),
+    Io(io::Error),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Build(error) => write!(f, "{}", error),
+            BatchError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<BuildError> for BatchError {
+    fn from(error: BuildError) -> Self {
+        BatchError::Build(error)
+    }
+}
+
+impl From<io::Error> for BatchError {
+    fn from(error: io::Error) -> Self {
+        BatchError::Io(error)
+    }
+}
+
+// Built-in weights by name: `uniform` gives every wall type a weight of 1,
+// and `ramp` weighs the wall types from 1 to 24 in the order of
+// `WallType::ALL`, as in the README
+pub fn get_preset(name: &str) -> Option<WallWeights> {
+    match name {
+        "uniform" => Some(WallWeights::from_fn(|_| 1)),
+        "ramp" => Some(WallWeights::from_fn(|wall_type| {
+            WallType::iter().position(|t| t == wall_type).unwrap_or(0) as u32 + 1
+        })),
+        _ => None,
+    }
+}
+
+// Generate every maze of the sweep in parallel, calling `on_record` with each
+// one in the order of sizes, then presets, then seeds. Stops at the first
+// error. Returns the number of mazes generated.
+pub fn generate<F>(sweep: &Sweep, mut on_record: F) -> Result<usize, BatchError>
+where
+    F: FnMut(Record) -> io::Result<()>,
+{
+    let jobs: Vec<(usize, usize, usize, u64)> = sweep
+        .sizes
+        .iter()
+        .flat_map(|&(width, height)| {
+            (0..sweep.presets.len()).flat_map(move |preset| {
+                sweep
+                    .seeds
+                    .iter()
+                    .map(move |&seed| (width, height, preset, seed))
+            })
+        })
+        .collect();
+    let threads = match sweep.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    for chunk in jobs.chunks(threads * CHUNK_SIZE) {
+        let share = chunk.len().div_ceil(threads);
+        let results: Vec<Vec<Result<Record, BuildError>>> = thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .chunks(share)
+                .map(|jobs| {
+                    scope.spawn(move || {
+                        jobs.iter()
+                            .map(|&(width, height, preset, seed)| {
+                                build_record(sweep, width, height, preset, seed)
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("maze generation panicked"))
+                .collect()
+        });
+        for record in results.into_iter().flatten() {
+            on_record(record?)?;
+        }
+    }
+
+    Ok(jobs.len())
+}
+
+// Generate every maze of the sweep and write it to `out` in the given format.
+// Returns the number of mazes written.
+pub fn export<W: Write>(sweep: &Sweep, format: Format, mut out: W) -> Result<usize, BatchError> {
+    if format == Format::Binary {
+        write_binary_header(&mut out, sweep)?;
+    }
+    let count = generate(sweep, |record| match format {
+        Format::JsonLines => writeln!(out, "{}", record.to_json(sweep)),
+        Format::Binary => write_binary_record(&mut out, &record),
+    })?;
+    out.flush()?;
+    Ok(count)
+}
+
+impl Record {
+    // The record as a single line JSON object, with the weights in the order
+    // of `WallType::ALL`
+    pub fn to_json(&self, sweep: &Sweep) -> String {
+        let (name, weights) = match sweep.presets.get(self.preset) {
+            Some((name, weights)) => (name.as_str(), Some(weights)),
+            None => ("", None),
+        };
+        let weights: Vec<String> = WallType::iter()
+            .map(|wall_type| weights.map_or(0, |w| w.get(wall_type)).to_string())
+            .collect();
+        let metric = |get: fn(&Metrics) -> usize| {
+            self.metrics
+                .as_ref()
+                .map_or("null".to_string(), |m| get(m).to_string())
+        };
+        format!(
+            "{{\"width\":{},\"height\":{},\"seed\":{},\"preset\":{},\"weights\":[{}],\
+             \"topology\":[{},{}],\"length\":{},\"turns\":{},\"dead_ends\":{},\"maze\":{}}}",
+            self.maze.width,
+            self.maze.height,
+            self.seed,
+            to_json_string(name),
+            weights.join(","),
+            to_json_string(get_boundary_name(self.maze.topology.horizontal)),
+            to_json_string(get_boundary_name(self.maze.topology.vertical)),
+            metric(|m| m.length),
+            metric(|m| m.turns),
+            metric(|m| m.dead_ends),
+            to_json_string(&self.maze.to_ascii()),
+        )
+    }
+}
+
+fn build_record(
+    sweep: &Sweep,
+    width: usize,
+    height: usize,
+    preset: usize,
+    seed: u64,
+) -> Result<Record, BuildError> {
+    let options = BuildOptions {
+        seed: Some(seed),
+        ..sweep.options.clone()
+    };
+    let config = sweep.presets[preset].1.clone();
+    let maze = Maze::build_with_options(width, height, config, options)?;
+    let metrics = maze.measure((0, 0), (height - 1, width - 1));
+    Ok(Record {
+        seed,
+        preset,
+        maze,
+        metrics,
+    })
+}
+
+fn write_binary_header<W: Write>(out: &mut W, sweep: &Sweep) -> io::Result<()> {
+    out.write_all(b"MAZB")?;
+    out.write_all(&1u16.to_le_bytes())?;
+    out.write_all(&(sweep.presets.len() as u16).to_le_bytes())?;
+    for (name, weights) in &sweep.presets {
+        out.write_all(&(name.len() as u16).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        for wall_type in WallType::iter() {
+            out.write_all(&weights.get(wall_type).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_binary_record<W: Write>(out: &mut W, record: &Record) -> io::Result<()> {
+    let maze = &record.maze;
+    out.write_all(&(maze.width as u32).to_le_bytes())?;
+    out.write_all(&(maze.height as u32).to_le_bytes())?;
+    out.write_all(&record.seed.to_le_bytes())?;
+    out.write_all(&(record.preset as u16).to_le_bytes())?;
+    for boundary in [maze.topology.horizontal, maze.topology.vertical] {
+        out.write_all(&[boundary as u8])?;
+    }
+    let metrics = match &record.metrics {
+        Some(m) => [m.length, m.turns, m.dead_ends].map(|value| value as u32),
+        None => [u32::MAX; 3],
+    };
+    for value in metrics {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    out.write_all(&(maze.crossings.len() as u32).to_le_bytes())?;
+    for crossing in &maze.crossings {
+        out.write_all(&(crossing.row as u32).to_le_bytes())?;
+        out.write_all(&(crossing.col as u32).to_le_bytes())?;
+        out.write_all(&[u8::from(crossing.over == Axis::Vertical)])?;
+    }
+
+    let mut bytes = Vec::new();
+    for (i, &wall) in maze.grid.iter().flatten().enumerate() {
+        if i % 8 == 0 {
+            bytes.push(0);
+        }
+        if wall {
+            *bytes.last_mut().unwrap() |= 0x80 >> (i % 8);
+        }
+    }
+    out.write_all(&bytes)
+}
+
+fn get_boundary_name(boundary: Boundary) -> &'static str {
+    match boundary {
+        Boundary::Closed => "closed",
+        Boundary::Wrapped => "wrapped",
+        Boundary::Twisted => "twisted",
+    }
+}
+
+// Quoted JSON string, escaping quotes, backslashes and control characters
+fn to_json_string(text: &str) -> String {
+    let mut res = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Topology;
+
+    // Reads little endian integers off the front of a byte slice
+    struct Reader<'a>(&'a [u8]);
+
+    impl Reader<'_> {
+        fn take<const N: usize>(&mut self) -> [u8; N] {
+            let (bytes, rest) = self.0.split_at(N);
+            self.0 = rest;
+            bytes.try_into().unwrap()
+        }
+
+        fn u8(&mut self) -> u8 {
+            self.take::<1>()[0]
+        }
+
+        fn u16(&mut self) -> u16 {
+            u16::from_le_bytes(self.take())
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_le_bytes(self.take())
+        }
+
+        fn u64(&mut self) -> u64 {
+            u64::from_le_bytes(self.take())
+        }
+    }
+
+    #[test]
+    fn binary_records_decode() {
+        let sweep = Sweep {
+            sizes: vec![(6, 5)],
+            presets: PRESETS
+                .iter()
+                .map(|&name| (name.to_string(), get_preset(name).unwrap()))
+                .collect(),
+            seeds: vec![7],
+            options: BuildOptions {
+                topology: Topology::TORUS,
+                weave_density: 0.3,
+                ..Default::default()
+            },
+            threads: 1,
+        };
+        let mut out = Vec::new();
+        export(&sweep, Format::Binary, &mut out).unwrap();
+        let mut records = Vec::new();
+        generate(&sweep, |record| {
+            records.push(record);
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(records
+            .iter()
+            .any(|record| !record.maze.crossings.is_empty()));
+
+        let mut reader = Reader(&out);
+        assert_eq!(&reader.take::<4>(), b"MAZB");
+        assert_eq!(reader.u16(), 1);
+        assert_eq!(reader.u16(), 2);
+        for (name, weights) in &sweep.presets {
+            let length = reader.u16() as usize;
+            assert_eq!(reader.0[..length], *name.as_bytes());
+            reader.0 = &reader.0[length..];
+            for wall_type in WallType::iter() {
+                assert_eq!(reader.u32(), weights.get(wall_type));
+            }
+        }
+
+        for record in &records {
+            let maze = &record.maze;
+            assert_eq!(reader.u32() as usize, maze.width);
+            assert_eq!(reader.u32() as usize, maze.height);
+            assert_eq!(reader.u64(), record.seed);
+            assert_eq!(reader.u16() as usize, record.preset);
+            assert_eq!(reader.u8(), 1);
+            assert_eq!(reader.u8(), 1);
+            let metrics = record.metrics.as_ref().unwrap();
+            assert_eq!(reader.u32() as usize, metrics.length);
+            assert_eq!(reader.u32() as usize, metrics.turns);
+            assert_eq!(reader.u32() as usize, metrics.dead_ends);
+            assert_eq!(reader.u32() as usize, maze.crossings.len());
+            for crossing in &maze.crossings {
+                assert_eq!(reader.u32() as usize, crossing.row);
+                assert_eq!(reader.u32() as usize, crossing.col);
+                assert_eq!(reader.u8(), u8::from(crossing.over == Axis::Vertical));
+            }
+            let positions = (maze.width * 2 + 1) * (maze.height * 2 + 1);
+            let bits = &reader.0[..positions.div_ceil(8)];
+            reader.0 = &reader.0[positions.div_ceil(8)..];
+            let grid: Vec<bool> = (0..positions)
+                .map(|i| bits[i / 8] & (0x80 >> (i % 8)) != 0)
+                .collect();
+            assert_eq!(grid, maze.grid.concat());
+        }
+        assert!(reader.0.is_empty());
+    }
+}
@@ -0,0 +1,629 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::maze::{Axis, Direction, Maze, Move};
+use crate::solve::is_open;
+
+// Hand kept on the wall by a wall follower
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+// Something a solver did, in the order it happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    // The walker moved between two cells
+    Move {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    // The walker left a mark on the passage it is about to take, which now
+    // carries `count` marks
+    Mark {
+        from: (usize, usize),
+        to: (usize, usize),
+        count: u8,
+    },
+    // A dead-end cell was filled in
+    Fill((usize, usize)),
+    // A passage was walled off to turn a loop into a dead end
+    Block {
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+}
+
+// Everything a solver did, and the path it found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    // Cells from the start to the goal without any detours, or `None` when
+    // the solver gave up
+    pub path: Option<Vec<(usize, usize)>>,
+}
+
+// Walk keeping one hand on the wall. Always finds the goal in a maze without
+// loops, but may walk in circles forever otherwise, in which case it gives
+// up as soon as it repeats itself.
+pub fn wall_follower(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    hand: Hand,
+) -> Trace {
+    let mut walker = Walker::new(start, Direction::North, hand);
+    if !is_open(maze, start) || !is_open(maze, goal) {
+        return walker.finish(false);
+    }
+
+    let mut seen = HashSet::new();
+    while walker.cell != goal {
+        if !seen.insert((walker.cell, walker.facing, walker.hand)) {
+            return walker.finish(false);
+        }
+        let moves = maze.moves(walker.cell.0, walker.cell.1);
+        let choice = [Turn::Hand, Turn::Straight, Turn::Away, Turn::Back]
+            .into_iter()
+            .find_map(|turn| find_move(&moves, walker.turn(turn)));
+        match choice {
+            Some(m) => walker.advance(m),
+            None => return walker.finish(false),
+        }
+    }
+    walker.finish(true)
+}
+
+// Walk in the `preferred` direction until blocked, then follow the wall with
+// the left hand until facing the preferred direction again with all turns
+// made so far cancelling out. Made to escape a maze through its border, so
+// it may never find a goal inside the maze, in which case it gives up after
+// a while.
+pub fn pledge(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    preferred: Direction,
+) -> Trace {
+    let mut walker = Walker::new(start, preferred, Hand::Left);
+    if !is_open(maze, start) || !is_open(maze, goal) {
+        return walker.finish(false);
+    }
+
+    // Sum of the turns made, counting a turn toward the hand on the wall as 1
+    let mut turns: i32 = 0;
+    let mut preferred = preferred;
+    let limit = maze.width * maze.height * 16;
+    for _ in 0..limit {
+        if walker.cell == goal {
+            return walker.finish(true);
+        }
+        let choices = if turns == 0 {
+            walker.facing = preferred;
+            [
+                (Turn::Straight, 0),
+                (Turn::Away, -1),
+                (Turn::Back, -2),
+                (Turn::Hand, 1),
+            ]
+        } else {
+            [
+                (Turn::Hand, 1),
+                (Turn::Straight, 0),
+                (Turn::Away, -1),
+                (Turn::Back, -2),
+            ]
+        };
+        let moves = maze.moves(walker.cell.0, walker.cell.1);
+        let choice = choices
+            .into_iter()
+            .find_map(|(turn, delta)| find_move(&moves, walker.turn(turn)).map(|m| (m, delta)));
+        let Some((m, delta)) = choice else {
+            return walker.finish(false);
+        };
+        turns += delta;
+        // The preferred direction turns over together with the walker
+        if m.mirrored {
+            preferred = mirror(preferred, m.direction.axis());
+        }
+        walker.advance(m);
+    }
+    let found = walker.cell == goal;
+    walker.finish(found)
+}
+
+// Mark every passage taken. Never enter a passage marked twice, prefer
+// unmarked passages, and turn back when arriving at a junction already
+// visited through a new passage. Finds the goal in any maze, and the
+// passages marked once then lead from the start to the goal.
+pub fn tremaux(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> Trace {
+    let mut walker = Walker::new(start, Direction::North, Hand::Left);
+    if !is_open(maze, start) || !is_open(maze, goal) {
+        return walker.finish(false);
+    }
+
+    let mut marks: HashMap<Passage, u8> = HashMap::new();
+    let mut arrived_by: Option<Passage> = None;
+    while walker.cell != goal {
+        let moves = maze.moves(walker.cell.0, walker.cell.1);
+        let count = |m: &Move| {
+            marks
+                .get(&get_passage(walker.cell, m))
+                .copied()
+                .unwrap_or(0)
+        };
+        let is_back = |m: &Move| Some(get_passage(walker.cell, m)) == arrived_by;
+        let back = moves.iter().find(|m| is_back(m));
+        let visited = moves.iter().any(|m| !is_back(m) && count(m) > 0);
+
+        let choice = if visited && back.is_some_and(|m| count(m) == 1) {
+            back
+        } else {
+            moves
+                .iter()
+                .find(|m| !is_back(m) && count(m) == 0)
+                .or(back.filter(|m| count(m) < 2))
+                .or_else(|| {
+                    moves
+                        .iter()
+                        .filter(|m| count(m) < 2)
+                        .min_by_key(|m| count(m))
+                })
+        };
+        let Some(&m) = choice else {
+            return walker.finish(false);
+        };
+
+        let passage = get_passage(walker.cell, &m);
+        let count = marks.entry(passage).or_insert(0);
+        *count += 1;
+        walker.events.push(TraceEvent::Mark {
+            from: walker.cell,
+            to: m.to,
+            count: *count,
+        });
+        arrived_by = Some(passage);
+        walker.advance(m);
+    }
+    walker.finish(true)
+}
+
+// Fill in every dead end other than the start and the goal until none are
+// left. In a maze without loops, only the path from the start to the goal
+// remains.
+pub fn dead_end_filling(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> Trace {
+    let mut filler = Filler::new(maze, start, goal);
+    filler.fill_dead_ends();
+    filler.finish()
+}
+
+// Like dead-end filling, but also wall off every loop hanging from a single
+// junction so that it becomes a dead end and gets filled in as well
+pub fn cul_de_sac_filling(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> Trace {
+    let mut filler = Filler::new(maze, start, goal);
+    loop {
+        filler.fill_dead_ends();
+        if !filler.block_cul_de_sac() {
+            break;
+        }
+    }
+    filler.finish()
+}
+
+// ========== Walkers ==========
+
+// A turn relative to the walker, who may see left and right swapped compared
+// to the maze after crossing a twisted border
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    Hand,
+    Straight,
+    Away,
+    Back,
+}
+
+struct Walker {
+    cell: (usize, usize),
+    facing: Direction,
+    // Hand on the wall, as seen from the maze
+    hand: Hand,
+    events: Vec<TraceEvent>,
+    // Cells walked so far, with every detour that came back cut out
+    path: Vec<(usize, usize)>,
+}
+
+impl Walker {
+    fn new(cell: (usize, usize), facing: Direction, hand: Hand) -> Self {
+        Walker {
+            cell,
+            facing,
+            hand,
+            events: Vec::new(),
+            path: vec![cell],
+        }
+    }
+
+    fn turn(&self, turn: Turn) -> Direction {
+        let toward_hand = match self.hand {
+            Hand::Left => turn_left(self.facing),
+            Hand::Right => turn_right(self.facing),
+        };
+        match turn {
+            Turn::Hand => toward_hand,
+            Turn::Straight => self.facing,
+            Turn::Away => toward_hand.opposite(),
+            Turn::Back => self.facing.opposite(),
+        }
+    }
+
+    fn advance(&mut self, m: Move) {
+        self.events.push(TraceEvent::Move {
+            from: self.cell,
+            to: m.to,
+        });
+        match self.path.iter().position(|&cell| cell == m.to) {
+            Some(i) => self.path.truncate(i + 1),
+            None => self.path.push(m.to),
+        }
+        self.cell = m.to;
+        self.facing = m.direction;
+        if m.mirrored {
+            self.hand = match self.hand {
+                Hand::Left => Hand::Right,
+                Hand::Right => Hand::Left,
+            };
+        }
+    }
+
+    fn finish(self, found: bool) -> Trace {
+        Trace {
+            events: self.events,
+            path: found.then_some(self.path),
+        }
+    }
+}
+
+fn find_move(moves: &[Move], direction: Direction) -> Option<Move> {
+    moves.iter().copied().find(|m| m.direction == direction)
+}
+
+fn turn_left(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+    }
+}
+
+fn turn_right(direction: Direction) -> Direction {
+    turn_left(direction).opposite()
+}
+
+// Direction as seen after crossing a twisted border while moving along `axis`
+fn mirror(direction: Direction, axis: Axis) -> Direction {
+    if direction.axis() == axis {
+        direction
+    } else {
+        direction.opposite()
+    }
+}
+
+// ========== Fillers ==========
+
+struct Filler<'a> {
+    maze: &'a Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    filled: Vec<bool>,
+    blocked: HashSet<Passage>,
+    events: Vec<TraceEvent>,
+}
+
+impl<'a> Filler<'a> {
+    fn new(maze: &'a Maze, start: (usize, usize), goal: (usize, usize)) -> Self {
+        Filler {
+            maze,
+            start,
+            goal,
+            filled: vec![false; maze.width * maze.height],
+            blocked: HashSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    // Moves out of a cell that are neither walled off nor lead to a filled cell
+    fn moves(&self, cell: (usize, usize)) -> Vec<Move> {
+        self.maze
+            .moves(cell.0, cell.1)
+            .into_iter()
+            .filter(|m| !self.is_filled(m.to) && !self.blocked.contains(&get_passage(cell, m)))
+            .collect()
+    }
+
+    fn is_filled(&self, (row, col): (usize, usize)) -> bool {
+        self.filled[row * self.maze.width + col]
+    }
+
+    fn can_fill(&self, cell: (usize, usize)) -> bool {
+        cell != self.start
+            && cell != self.goal
+            && is_open(self.maze, cell)
+            && !self.is_filled(cell)
+            && self.moves(cell).len() <= 1
+    }
+
+    fn fill_dead_ends(&mut self) {
+        let mut queue: VecDeque<(usize, usize)> = (0..self.maze.height)
+            .flat_map(|row| (0..self.maze.width).map(move |col| (row, col)))
+            .collect();
+        while let Some(cell) = queue.pop_front() {
+            if !self.can_fill(cell) {
+                continue;
+            }
+            // Only the cell the dead end leads to may become a dead end next
+            queue.extend(self.moves(cell).iter().map(|m| m.to));
+            self.filled[cell.0 * self.maze.width + cell.1] = true;
+            self.events.push(TraceEvent::Fill(cell));
+        }
+    }
+
+    // Wall off the entrance of one loop that leaves a junction and comes
+    // back to it without passing another junction, the start or the goal.
+    // Returns whether such a loop was found.
+    fn block_cul_de_sac(&mut self) -> bool {
+        for row in 0..self.maze.height {
+            for col in 0..self.maze.width {
+                let junction = (row, col);
+                if self.is_filled(junction) || !is_open(self.maze, junction) {
+                    continue;
+                }
+                let exits = self.moves(junction);
+                if exits.len() < 3 {
+                    continue;
+                }
+                for exit in exits {
+                    if self.follow_corridor(junction, exit) == Some(junction) {
+                        self.blocked.insert(get_passage(junction, &exit));
+                        self.events.push(TraceEvent::Block {
+                            from: junction,
+                            to: exit.to,
+                        });
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Follow a passage without side branches and return the junction it ends
+    // at, or `None` if it ends at the start or the goal
+    fn follow_corridor(&self, from: (usize, usize), exit: Move) -> Option<(usize, usize)> {
+        let mut arrived_by = get_passage(from, &exit);
+        let mut cell = exit.to;
+        for _ in 0..self.filled.len() {
+            if cell == self.start || cell == self.goal {
+                return None;
+            }
+            let moves = self.moves(cell);
+            if moves.len() != 2 {
+                return Some(cell);
+            }
+            let next = moves
+                .into_iter()
+                .find(|m| get_passage(cell, m) != arrived_by)?;
+            arrived_by = get_passage(cell, &next);
+            cell = next.to;
+        }
+        None
+    }
+
+    // The path from the start to the goal through cells not filled in
+    fn finish(self) -> Trace {
+        let mut parents = HashMap::new();
+        let mut queue = VecDeque::from([self.start]);
+        let mut found = false;
+        while let Some(cell) = queue.pop_front() {
+            if !is_open(self.maze, cell) || !is_open(self.maze, self.goal) {
+                break;
+            }
+            if cell == self.goal {
+                found = true;
+                break;
+            }
+            for m in self.moves(cell) {
+                if m.to != self.start && !parents.contains_key(&m.to) {
+                    parents.insert(m.to, cell);
+                    queue.push_back(m.to);
+                }
+            }
+        }
+
+        let path = found.then(|| {
+            let mut path = vec![self.goal];
+            while let Some(&parent) = parents.get(&path[path.len() - 1]) {
+                path.push(parent);
+            }
+            path.reverse();
+            path
+        });
+        Trace {
+            events: self.events,
+            path,
+        }
+    }
+}
+
+// ========== Passages ==========
+
+// A passage between two cells, named by the end that sorts first together
+// with the direction it leaves in, so that both ends give the same name
+type Passage = ((usize, usize), usize);
+
+fn get_passage(from: (usize, usize), m: &Move) -> Passage {
+    let forward = (from, m.direction as usize);
+    let backward = (m.to, m.direction.opposite() as usize);
+    forward.min(backward)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildOptions, WallWeights};
+    use crate::solve;
+
+    // Seeded maze without loops
+    fn build(seed: u64) -> Maze {
+        let options = BuildOptions {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        Maze::build_with_options(8, 6, WallWeights::from_fn(|_| 1), options).unwrap()
+    }
+
+    fn count_moves(trace: &Trace) -> usize {
+        trace
+            .events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Move { .. }))
+            .count()
+    }
+
+    // Every cell open, leaving a post in the middle of each 2x2 square
+    const OPEN: &str = "
+        +---+---+---+
+        |           |
+        +   +   +   +
+        |           |
+        +   +   +   +
+        |           |
+        +---+---+---+
+    ";
+
+    // A corridor along the top with a loop hanging below it from (1, 1)
+    const CUL_DE_SAC: &str = "
+        +---+---+---+---+
+        |               |
+        +   +   +---+   +
+        |   |       |   |
+        +   +   +   +   +
+        |   |       |   |
+        +---+---+---+---+
+    ";
+
+    #[test]
+    fn wall_followers_solve_mazes_without_loops() {
+        for seed in 0..10 {
+            let maze = build(seed);
+            let shortest = solve::bfs(&maze, (0, 0), (5, 7)).unwrap().path;
+            for hand in [Hand::Left, Hand::Right] {
+                let trace = wall_follower(&maze, (0, 0), (5, 7), hand);
+                assert_eq!(trace.path.as_ref(), Some(&shortest), "{:?}", hand);
+            }
+        }
+    }
+
+    #[test]
+    fn wall_followers_give_up_walking_in_circles() {
+        let maze = Maze::from_ascii(OPEN).unwrap();
+        let trace = wall_follower(&maze, (0, 0), (1, 1), Hand::Left);
+        assert_eq!(trace.path, None);
+        assert!(count_moves(&trace) > 0);
+    }
+
+    #[test]
+    fn pledge_counts_a_lone_left_turn_as_one() {
+        // The start only opens to the left of the preferred direction. After
+        // turning left there and right at (2, 1), the turns cancel out and
+        // the walker heads north again instead of following the wall west.
+        let maze = Maze::from_ascii(
+            "
+            +---+---+---+
+            |   | G     |
+            +   +   +   +
+            |       |   |
+            +   +   +---+
+            |   |     S |
+            +---+---+---+
+            ",
+        )
+        .unwrap();
+        let trace = pledge(&maze, (2, 2), (0, 1), Direction::North);
+        assert_eq!(trace.path, Some(vec![(2, 2), (2, 1), (1, 1), (0, 1)]));
+        assert_eq!(count_moves(&trace), 3);
+    }
+
+    #[test]
+    fn pledge_walks_straight_when_nothing_is_in_the_way() {
+        let maze = Maze::from_ascii(OPEN).unwrap();
+        let trace = pledge(&maze, (2, 1), (0, 1), Direction::North);
+        assert_eq!(trace.path, Some(vec![(2, 1), (1, 1), (0, 1)]));
+    }
+
+    #[test]
+    fn tremaux_solves_mazes_with_loops() {
+        let maze = Maze::from_ascii(OPEN).unwrap();
+        for goal in [(1, 1), (2, 2), (0, 2)] {
+            let trace = tremaux(&maze, (0, 0), goal);
+            let path = trace.path.unwrap();
+            assert_eq!((path[0], path[path.len() - 1]), ((0, 0), goal));
+            for pair in path.windows(2) {
+                assert!(maze.neighbors(pair[0].0, pair[0].1).contains(&pair[1]));
+            }
+            assert!(trace
+                .events
+                .iter()
+                .all(|event| !matches!(event, TraceEvent::Mark { count, .. } if *count > 2)));
+        }
+
+        for seed in 0..10 {
+            let maze = build(seed);
+            let shortest = solve::bfs(&maze, (0, 0), (5, 7)).unwrap().path;
+            assert_eq!(tremaux(&maze, (0, 0), (5, 7)).path, Some(shortest));
+        }
+    }
+
+    #[test]
+    fn dead_end_filling_leaves_only_the_path() {
+        for seed in 0..10 {
+            let maze = build(seed);
+            let shortest = solve::bfs(&maze, (0, 0), (5, 7)).unwrap().path;
+            let trace = dead_end_filling(&maze, (0, 0), (5, 7));
+            let filled = trace
+                .events
+                .iter()
+                .filter(|event| matches!(event, TraceEvent::Fill(_)))
+                .count();
+            assert_eq!(filled, 48 - shortest.len());
+            assert_eq!(trace.path, Some(shortest));
+        }
+    }
+
+    #[test]
+    fn cul_de_sac_filling_fills_hanging_loops() {
+        let maze = Maze::from_ascii(CUL_DE_SAC).unwrap();
+        let loop_cells = [(1, 2), (2, 1), (2, 2)];
+        let filled = |trace: &Trace| -> Vec<(usize, usize)> {
+            trace
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    TraceEvent::Fill(cell) => Some(*cell),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let trace = dead_end_filling(&maze, (0, 0), (0, 3));
+        assert!(loop_cells.iter().all(|cell| !filled(&trace).contains(cell)));
+
+        let trace = cul_de_sac_filling(&maze, (0, 0), (0, 3));
+        assert!(loop_cells.iter().all(|cell| filled(&trace).contains(cell)));
+        assert!(trace
+            .events
+            .iter()
+            .any(|event| matches!(event, TraceEvent::Block { from: (1, 1), .. })));
+        assert_eq!(trace.path, Some(vec![(0, 0), (0, 1), (0, 2), (0, 3)]));
+    }
+}
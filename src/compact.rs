@@ -0,0 +1,273 @@
+use crate::maze::{
+    get_adjacent_cell, get_constrained_edge_id, get_edge_coord, get_edge_count, get_edge_id,
+    Boundary, BuildError, BuildOptions, Crossing, Direction, Edge, Maze, Topology, WallWeights,
+};
+
+// A maze stored as one bit per edge and one bit per cell, about a tenth of
+// the memory taken by the raster of a `Maze`. The raster is worked out on
+// demand, one position or one row at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactMaze {
+    pub width: usize,
+    pub height: usize,
+    pub topology: Topology,
+    // Cells where one passage runs over another
+    pub crossings: Vec<Crossing>,
+    // Set for every edge with a wall, by edge ID
+    walls: Vec<u64>,
+    // Set for every excluded cell, row by row
+    excluded: Vec<u64>,
+}
+
+impl Maze {
+    // Same as `build_with_options`, storing the maze as a `CompactMaze`
+    // without ever drawing the raster. Only the stored maze is compact: the
+    // generation itself still keeps a byte per edge, the edge weights and the
+    // sets of joined cells, which take several times the memory of the
+    // raster at their peak.
+    pub fn build_compact(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: BuildOptions,
+    ) -> Result<CompactMaze, BuildError> {
+        let carved = Maze::carve(width, height, config, options, |_| {})?;
+        let excluded = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| !carved.mask.contains(row, col));
+        Ok(CompactMaze {
+            width,
+            height,
+            topology: carved.topology,
+            crossings: carved.crossings,
+            walls: pack(carved.edges.into_iter()),
+            excluded: pack(excluded),
+        })
+    }
+
+    // Store the maze as a `CompactMaze`, which `CompactMaze::to_maze` turns
+    // back into the same maze. Openings in a closed border, which
+    // `from_ascii` keeps for entrances and exits, are closed.
+    pub fn to_compact(&self) -> CompactMaze {
+        let (width, height, topology) = (self.width, self.height, self.topology);
+        let walls = (0..get_edge_count(width, height, topology)).map(|id| {
+            get_edge_coord(width, height, topology, id)
+                .is_none_or(|(row, col)| self.grid[row + 1][col + 1])
+        });
+        let excluded = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| self.grid[row * 2 + 1][col * 2 + 1]);
+        CompactMaze {
+            width,
+            height,
+            topology,
+            crossings: self.crossings.clone(),
+            walls: pack(walls),
+            excluded: pack(excluded),
+        }
+    }
+}
+
+impl CompactMaze {
+    // Draw the whole raster
+    pub fn to_maze(&self) -> Maze {
+        Maze {
+            width: self.width,
+            height: self.height,
+            grid: self.rows().collect(),
+            topology: self.topology,
+            crossings: self.crossings.clone(),
+        }
+    }
+
+    // Whether there is a wall on the edge, `None` if the edge lies outside
+    // the maze or on a closed border
+    pub fn wall(&self, edge: Edge) -> Option<bool> {
+        let (row, col, direction) = match edge {
+            Edge::East(row, col) => (row, col, Direction::East),
+            Edge::South(row, col) => (row, col, Direction::South),
+        };
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        get_adjacent_cell(self.width, self.height, self.topology, row, col, direction)?;
+        let id = get_constrained_edge_id(self.width, self.height, self.topology, edge)?;
+        Some(get_bit(&self.walls, id))
+    }
+
+    // Whether the cell is left out of the maze, `None` outside the maze
+    pub fn is_excluded(&self, row: usize, col: usize) -> Option<bool> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        Some(get_bit(&self.excluded, row * self.width + col))
+    }
+
+    // The value of `Maze::grid` at a raster position, `None` outside the
+    // raster
+    pub fn raster(&self, row: usize, col: usize) -> Option<bool> {
+        let (width, height, topology) = (self.width, self.height, self.topology);
+        if row > height * 2 || col > width * 2 {
+            return None;
+        }
+        match (row % 2, col % 2) {
+            (0, 0) => return Some(true),
+            (1, 1) => return self.is_excluded(row / 2, col / 2),
+            _ => {}
+        }
+
+        // Lattice position of the edge, where a position on the border is
+        // the edge crossing the seam there
+        let (lattice_row, lattice_col) = if col == 0 || col == width * 2 {
+            match topology.horizontal {
+                Boundary::Closed => return Some(true),
+                Boundary::Wrapped => (row - 1, width * 2 - 1),
+                Boundary::Twisted if col == 0 => (height * 2 - 1 - row, width * 2 - 1),
+                Boundary::Twisted => (row - 1, width * 2 - 1),
+            }
+        } else if row == 0 || row == height * 2 {
+            match topology.vertical {
+                Boundary::Closed => return Some(true),
+                Boundary::Wrapped => (height * 2 - 1, col - 1),
+                Boundary::Twisted if row == 0 => (height * 2 - 1, width * 2 - 1 - col),
+                Boundary::Twisted => (height * 2 - 1, col - 1),
+            }
+        } else {
+            (row - 1, col - 1)
+        };
+        let id = get_edge_id(width, height, topology, lattice_row, lattice_col)?;
+        Some(get_bit(&self.walls, id))
+    }
+
+    // Rows of the raster, drawn one at a time
+    pub fn rows(&self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        (0..=self.height * 2).map(move |row| {
+            (0..=self.width * 2)
+                .map(|col| self.raster(row, col).unwrap_or(true))
+                .collect()
+        })
+    }
+}
+
+// Bits in order, 64 to a word starting from the lowest bit
+fn pack<I: Iterator<Item = bool>>(bits: I) -> Vec<u64> {
+    let mut res = Vec::new();
+    for (i, bit) in bits.enumerate() {
+        if i % 64 == 0 {
+            res.push(0);
+        }
+        if bit {
+            res[i / 64] |= 1 << (i % 64);
+        }
+    }
+    res
+}
+
+fn get_bit(bits: &[u64], i: usize) -> bool {
+    bits.get(i / 64)
+        .is_some_and(|word| word & (1 << (i % 64)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask::CellMask;
+
+    const TOPOLOGIES: [Topology; 5] = [
+        Topology::PLANE,
+        Topology::CYLINDER,
+        Topology::TORUS,
+        Topology::MOBIUS,
+        Topology::KLEIN,
+    ];
+
+    fn get_options(topology: Topology, weave_density: f64, seed: u64) -> BuildOptions {
+        BuildOptions {
+            topology,
+            weave_density,
+            seed: Some(seed),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compact_mazes_round_trip() {
+        let config = WallWeights::from_fn(|_| 1);
+        for topology in TOPOLOGIES {
+            for weave_density in [0.0, 0.5] {
+                for seed in 0..10 {
+                    let options = get_options(topology, weave_density, seed);
+                    let maze = Maze::build_with_options(7, 5, config.clone(), options).unwrap();
+                    let options = get_options(topology, weave_density, seed);
+                    let compact = Maze::build_compact(7, 5, config.clone(), options).unwrap();
+
+                    assert_eq!(maze.to_compact(), compact, "{:?}", topology);
+                    let unpacked = compact.to_maze();
+                    assert_eq!(unpacked.grid, maze.grid, "{:?}", topology);
+                    assert_eq!(unpacked.topology, maze.topology);
+                    assert_eq!(unpacked.crossings, maze.crossings);
+                    for (row, line) in maze.grid.iter().enumerate() {
+                        for (col, &wall) in line.iter().enumerate() {
+                            assert_eq!(compact.raster(row, col), Some(wall));
+                        }
+                    }
+                    assert_eq!(compact.raster(11, 0), None);
+                    assert_eq!(compact.raster(0, 15), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compact_walls_match_the_maze() {
+        let config = WallWeights::from_fn(|_| 1);
+        for topology in TOPOLOGIES {
+            let options = get_options(topology, 0.0, 1);
+            let maze = Maze::build_with_options(6, 4, config.clone(), options).unwrap();
+            let compact = maze.to_compact();
+            for row in 0..4 {
+                for col in 0..6 {
+                    assert_eq!(compact.is_excluded(row, col), Some(false));
+                    for edge in [Edge::East(row, col), Edge::South(row, col)] {
+                        let expected = maze.wall(edge);
+                        assert_eq!(compact.wall(edge), expected, "{:?}", edge);
+                    }
+                }
+            }
+            assert_eq!(compact.is_excluded(4, 0), None);
+        }
+    }
+
+    #[test]
+    fn excluded_cells_round_trip() {
+        let mut mask = CellMask::new(6, 5);
+        mask.exclude_rect(1, 2, 2, 2);
+        for topology in TOPOLOGIES {
+            let options = BuildOptions {
+                mask: Some(mask.clone()),
+                ..get_options(topology, 0.0, 2)
+            };
+            let config = WallWeights::from_fn(|_| 1);
+            let maze = Maze::build_with_options(6, 5, config, options).unwrap();
+            let compact = maze.to_compact();
+            assert_eq!(compact.to_maze().grid, maze.grid, "{:?}", topology);
+            assert_eq!(compact.is_excluded(1, 2), Some(true));
+            assert_eq!(compact.is_excluded(0, 2), Some(false));
+        }
+    }
+
+    // One bit per edge and one per cell takes about a tenth of the one byte
+    // per raster position of `Maze::grid`
+    #[test]
+    fn compact_mazes_take_a_tenth_of_the_raster() {
+        let config = WallWeights::from_fn(|_| 1);
+        for topology in TOPOLOGIES {
+            let options = get_options(topology, 0.0, 0);
+            let maze = Maze::build_with_options(100, 100, config.clone(), options).unwrap();
+            let compact = maze.to_compact();
+            let raster_bytes: usize = maze.grid.iter().map(Vec::len).sum();
+            let compact_bytes = (compact.walls.len() + compact.excluded.len()) * 8;
+            assert!(compact_bytes * 10 <= raster_bytes, "{:?}", topology);
+        }
+    }
+}
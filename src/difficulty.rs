@@ -0,0 +1,310 @@
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::maze::{BuildError, BuildOptions, Direction, Edge, Maze, Move, Symmetry, WallWeights};
+use crate::solve;
+
+// Requirements on the solution of a generated maze
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub start: (usize, usize),
+    // Defaults to the bottom right cell
+    pub goal: Option<(usize, usize)>,
+    // Cells travelled through from the start to the goal along the shortest
+    // path, both included
+    pub min_length: usize,
+    // Changes of direction along the shortest path
+    pub min_turns: usize,
+    // Mazes generated before giving up
+    pub max_attempts: usize,
+    // Local changes tried on every generated maze that misses the target.
+    // Each change opens a wall and closes another one on the loop this
+    // creates, and is kept unless it moves the maze away from the target.
+    // Changes ignore `WallWeights` and are never made to symmetric mazes.
+    pub max_edits: usize,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target {
+            start: (0, 0),
+            goal: None,
+            min_length: 0,
+            min_turns: 0,
+            max_attempts: 100,
+            max_edits: 0,
+        }
+    }
+}
+
+// How hard a maze is to solve between two cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metrics {
+    // Cells travelled through along the shortest path, both ends included.
+    // Passing under a crossing counts the crossing cell.
+    pub length: usize,
+    // Changes of direction along the shortest path
+    pub turns: usize,
+    // Cells with a single way out, other than the start and goal
+    pub dead_ends: usize,
+    // Mazes generated to reach these metrics
+    pub attempts: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetError {
+    Build(BuildError),
+    // The start or goal lies outside the maze or on an excluded cell
+    InvalidCell,
+    // No maze met the target within `max_attempts`. Holds the metrics of the
+    // maze that came closest.
+    NotMet(Metrics),
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetError::Build(error) => write!(f, "{}", error),
+            TargetError::InvalidCell => write!(f, "start or goal is not an open cell"),
+            TargetError::NotMet(best) => write!(
+                f,
+                "target not met after {} attempts, best path has {} cells and {} turns",
+                best.attempts, best.length, best.turns
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+impl From<BuildError> for TargetError {
+    fn from(error: BuildError) -> Self {
+        TargetError::Build(error)
+    }
+}
+
+impl Maze {
+    // Generate mazes as with `build_with_options` until one meets the target,
+    // returning it with its metrics. With a seed in `options`, every attempt
+    // and edit is drawn from it.
+    pub fn build_with_target(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: BuildOptions,
+        target: &Target,
+    ) -> Result<(Maze, Metrics), TargetError> {
+        let goal = target.goal.unwrap_or((height.max(1) - 1, width.max(1) - 1));
+        let shortfall = |m: &Metrics| {
+            target.min_length.saturating_sub(m.length) + target.min_turns.saturating_sub(m.turns)
+        };
+        let editable = options.symmetry == Symmetry::None;
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut best: Option<Metrics> = None;
+        for attempt in 1..=target.max_attempts.max(1) {
+            let attempt_options = BuildOptions {
+                seed: Some(rng.gen()),
+                ..options.clone()
+            };
+            let mut maze =
+                Maze::build_with_options(width, height, config.clone(), attempt_options)?;
+            let mut metrics = maze
+                .measure(target.start, goal)
+                .ok_or(TargetError::InvalidCell)?;
+            let edits = if editable { target.max_edits } else { 0 };
+            for _ in 0..edits {
+                if shortfall(&metrics) == 0 {
+                    break;
+                }
+                if let Some(edited) =
+                    maze.edit_towards(target.start, goal, &options, &mut rng, |m| {
+                        shortfall(m) <= shortfall(&metrics)
+                    })
+                {
+                    metrics = edited;
+                }
+            }
+            metrics.attempts = attempt;
+            if shortfall(&metrics) == 0 {
+                return Ok((maze, metrics));
+            }
+            if best.is_none_or(|b| shortfall(&metrics) < shortfall(&b)) {
+                best = Some(metrics);
+            }
+        }
+        let mut best = best.ok_or(TargetError::InvalidCell)?;
+        best.attempts = target.max_attempts.max(1);
+        Err(TargetError::NotMet(best))
+    }
+
+    // Metrics of the shortest path between two cells, `None` if either is
+    // not an open cell or the goal cannot be reached
+    pub fn measure(&self, start: (usize, usize), goal: (usize, usize)) -> Option<Metrics> {
+        let solution = solve::dijkstra(self, start, goal, solve::distance)?;
+        let mut turns = 0;
+        let mut previous = None;
+        for pair in solution.path.windows(2) {
+            let m = self
+                .moves(pair[0].0, pair[0].1)
+                .into_iter()
+                .find(|m| m.to == pair[1])?;
+            if previous.is_some_and(|d| d != m.direction) {
+                turns += 1;
+            }
+            previous = Some(m.direction);
+        }
+        let dead_ends = (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+            .filter(|&cell| cell != start && cell != goal && solve::is_open(self, cell))
+            .filter(|&(row, col)| self.moves(row, col).len() == 1)
+            .count();
+        Some(Metrics {
+            length: solution.cost as usize + 1,
+            turns,
+            dead_ends,
+            attempts: 1,
+        })
+    }
+
+    // Open a random wall and close another one on the loop this creates,
+    // keeping the change only if `accept` holds for the new metrics. Walls
+    // pinned by `options` are left alone.
+    fn edit_towards<F>(
+        &mut self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        options: &BuildOptions,
+        rng: &mut StdRng,
+        accept: F,
+    ) -> Option<Metrics>
+    where
+        F: Fn(&Metrics) -> bool,
+    {
+        let closed: Vec<Edge> = self
+            .edges()
+            .filter(|&edge| self.wall(edge) == Some(true) && !options.fixed_walls.contains(&edge))
+            .collect();
+        let &opened = closed.choose(rng)?;
+        let (near, far) = match opened {
+            Edge::East(row, col) => ((row, col), self.adjacent(row, col, Direction::East)?.0),
+            Edge::South(row, col) => ((row, col), self.adjacent(row, col, Direction::South)?.0),
+        };
+
+        // The loop is the path already joining both sides of the wall
+        let path = solve::bfs(self, near, far)?.path;
+        let mut on_loop = Vec::new();
+        for pair in path.windows(2) {
+            let m = self
+                .moves(pair[0].0, pair[0].1)
+                .into_iter()
+                .find(|m| m.to == pair[1])?;
+            if m.length == 1 {
+                on_loop.push(get_edge(pair[0], &m));
+            }
+        }
+        on_loop.retain(|edge| !options.forced_openings.contains(edge));
+        let &closing = on_loop.choose(rng)?;
+
+        self.set_wall(opened, false).ok()?;
+        if self.set_wall(closing, true).is_err() {
+            self.set_wall(opened, true).ok()?;
+            return None;
+        }
+        match self.measure(start, goal) {
+            Some(metrics) if accept(&metrics) => Some(metrics),
+            _ => {
+                self.set_wall(closing, false).ok()?;
+                self.set_wall(opened, true).ok()?;
+                None
+            }
+        }
+    }
+}
+
+// Wall crossed by a move of a single cell from `from`
+fn get_edge(from: (usize, usize), m: &Move) -> Edge {
+    match m.direction {
+        Direction::East => Edge::East(from.0, from.1),
+        Direction::South => Edge::South(from.0, from.1),
+        Direction::West => Edge::East(m.to.0, m.to.1),
+        Direction::North => Edge::South(m.to.0, m.to.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_options(seed: u64) -> BuildOptions {
+        BuildOptions {
+            seed: Some(seed),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn edits_reach_lengths_regeneration_misses() {
+        let config = WallWeights::from_fn(|_| 1);
+        for seed in 0..3 {
+            let regenerate = Target {
+                min_length: 40,
+                max_attempts: 10,
+                ..Default::default()
+            };
+            let res = Maze::build_with_target(8, 8, config.clone(), get_options(seed), &regenerate);
+            assert!(matches!(res, Err(TargetError::NotMet(_))), "seed {}", seed);
+
+            let edit = Target {
+                max_edits: 500,
+                ..regenerate
+            };
+            let (maze, metrics) =
+                Maze::build_with_target(8, 8, config.clone(), get_options(seed), &edit).unwrap();
+            assert!(metrics.length >= 40);
+            assert_eq!(maze.measure((0, 0), (7, 7)).unwrap().length, metrics.length);
+        }
+    }
+
+    #[test]
+    fn unmet_targets_report_the_closest_maze() {
+        let config = WallWeights::from_fn(|_| 1);
+        let target = Target {
+            min_length: 60,
+            min_turns: 40,
+            max_attempts: 20,
+            ..Default::default()
+        };
+        let Err(TargetError::NotMet(best)) =
+            Maze::build_with_target(8, 8, config.clone(), get_options(3), &target)
+        else {
+            panic!("target should not be met");
+        };
+        assert_eq!(best.attempts, 20);
+
+        // Every attempt is seeded from the same generator
+        let mut rng = StdRng::seed_from_u64(3);
+        let shortfalls: Vec<(usize, Metrics)> = (0..20)
+            .map(|_| {
+                let options = get_options(rng.gen());
+                let maze = Maze::build_with_options(8, 8, config.clone(), options).unwrap();
+                let metrics = maze.measure((0, 0), (7, 7)).unwrap();
+                let shortfall =
+                    60usize.saturating_sub(metrics.length) + 40usize.saturating_sub(metrics.turns);
+                (shortfall, metrics)
+            })
+            .collect();
+        // The first of the closest attempts is kept
+        let (_, mut closest) = shortfalls
+            .into_iter()
+            .min_by_key(|&(shortfall, _)| shortfall)
+            .unwrap();
+        closest.attempts = 20;
+        assert_eq!(best, closest);
+    }
+}
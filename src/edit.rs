@@ -0,0 +1,268 @@
+use std::fmt;
+
+use disjoint_sets::UnionFind;
+
+use crate::maze::{get_adjacent_cell, Boundary, Direction, Edge, Maze};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    // The edge lies outside the maze or on a closed border, touches an
+    // excluded cell, or surrounds a crossing
+    InvalidEdge,
+    // Adding the wall would cut the maze into pieces
+    Disconnected,
+    // The mazes differ in size or topology
+    Mismatch,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::InvalidEdge => write!(f, "edge cannot be edited"),
+            EditError::Disconnected => write!(f, "wall would disconnect the maze"),
+            EditError::Mismatch => write!(f, "mazes differ in size or topology"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+impl Maze {
+    // Whether there is a wall on the edge, `None` if the edge lies outside
+    // the maze or on a closed border
+    pub fn wall(&self, edge: Edge) -> Option<bool> {
+        let positions = self.get_wall_positions(edge)?;
+        Some(self.grid[positions[0].0][positions[0].1])
+    }
+
+    // Add or remove the wall on an edge. Removing a wall may create a loop,
+    // while adding one is refused if some cells could no longer be reached,
+    // leaving the maze unchanged.
+    pub fn set_wall(&mut self, edge: Edge, wall: bool) -> Result<(), EditError> {
+        let positions = self.get_editable_positions(edge)?;
+        let (row, col) = positions[0];
+        if self.grid[row][col] == wall {
+            return Ok(());
+        }
+        for &(row, col) in &positions {
+            self.grid[row][col] = wall;
+        }
+        if wall && !self.is_connected() {
+            for &(row, col) in &positions {
+                self.grid[row][col] = false;
+            }
+            return Err(EditError::Disconnected);
+        }
+        Ok(())
+    }
+
+    // Add the wall on an edge if it is open, or remove it otherwise, as with
+    // `set_wall`. Returns whether there is a wall afterwards.
+    pub fn toggle_wall(&mut self, edge: Edge) -> Result<bool, EditError> {
+        let wall = !self.wall(edge).ok_or(EditError::InvalidEdge)?;
+        self.set_wall(edge, wall)?;
+        Ok(wall)
+    }
+
+    // Whether every cell that is not excluded can be reached from every
+    // other one
+    pub fn is_connected(&self) -> bool {
+        let mut cells = UnionFind::<usize>::new(self.width * self.height);
+        let mut open = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.grid[row * 2 + 1][col * 2 + 1] {
+                    continue;
+                }
+                open.push(row * self.width + col);
+                for m in self.moves(row, col) {
+                    cells.union(row * self.width + col, m.to.0 * self.width + m.to.1);
+                }
+            }
+        }
+        open.iter().all(|&id| cells.equiv(id, open[0]))
+    }
+
+    // Edges where one maze has a wall and the other does not, in the order
+    // of `edges`. Crossings are not compared.
+    pub fn diff(&self, other: &Maze) -> Result<Vec<Edge>, EditError> {
+        if (self.width, self.height, self.topology) != (other.width, other.height, other.topology) {
+            return Err(EditError::Mismatch);
+        }
+        Ok(self
+            .edges()
+            .filter(|&edge| self.wall(edge) != other.wall(edge))
+            .collect())
+    }
+
+    // Every edge of the maze, row by row, including edges crossing a joined
+    // border
+    pub fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        let east_count = self.width - usize::from(self.topology.horizontal == Boundary::Closed);
+        let south_count = self.height - usize::from(self.topology.vertical == Boundary::Closed);
+        (0..self.height).flat_map(move |row| {
+            let east = (0..east_count).map(move |col| Edge::East(row, col));
+            let south = (0..self.width)
+                .filter(move |_| row < south_count)
+                .map(move |col| Edge::South(row, col));
+            east.chain(south)
+        })
+    }
+
+    // Raster positions of a wall, on both borders when it crosses a seam.
+    // `None` if the edge lies outside the maze or on a closed border.
+    pub(crate) fn get_wall_positions(&self, edge: Edge) -> Option<Vec<(usize, usize)>> {
+        let (row, col, direction) = get_origin(edge);
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        let ((far_row, far_col), _) =
+            get_adjacent_cell(self.width, self.height, self.topology, row, col, direction)?;
+        let mut res = vec![match direction {
+            Direction::East => (row * 2 + 1, col * 2 + 2),
+            _ => (row * 2 + 2, col * 2 + 1),
+        }];
+        match direction {
+            Direction::East if col + 1 == self.width => res.push((far_row * 2 + 1, 0)),
+            Direction::South if row + 1 == self.height => res.push((0, far_col * 2 + 1)),
+            _ => {}
+        }
+        Some(res)
+    }
+
+    // Raster positions of a wall that may be edited, which excludes walls of
+    // excluded cells and walls around a crossing
+    fn get_editable_positions(&self, edge: Edge) -> Result<Vec<(usize, usize)>, EditError> {
+        let positions = self
+            .get_wall_positions(edge)
+            .ok_or(EditError::InvalidEdge)?;
+        let (row, col, direction) = get_origin(edge);
+        let ((far_row, far_col), _) =
+            get_adjacent_cell(self.width, self.height, self.topology, row, col, direction)
+                .ok_or(EditError::InvalidEdge)?;
+        for (row, col) in [(row, col), (far_row, far_col)] {
+            let excluded = self.grid[row * 2 + 1][col * 2 + 1];
+            let crossing = self.crossings.iter().any(|c| (c.row, c.col) == (row, col));
+            if excluded || crossing {
+                return Err(EditError::InvalidEdge);
+            }
+        }
+        Ok(positions)
+    }
+}
+
+// Cell on the west or north side of an edge, and the direction of the edge
+// from it
+fn get_origin(edge: Edge) -> (usize, usize, Direction) {
+    match edge {
+        Edge::East(row, col) => (row, col, Direction::East),
+        Edge::South(row, col) => (row, col, Direction::South),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildOptions, Topology, WallWeights};
+
+    fn build(topology: Topology, seed: u64) -> Maze {
+        let options = BuildOptions {
+            topology,
+            seed: Some(seed),
+            ..Default::default()
+        };
+        Maze::build_with_options(6, 5, WallWeights::from_fn(|_| 1), options).unwrap()
+    }
+
+    #[test]
+    fn walls_that_disconnect_the_maze_are_refused() {
+        let mut maze = build(Topology::PLANE, 1);
+        let grid = maze.grid.clone();
+        // Every opening of a tree is the only way between its two cells
+        let edges: Vec<Edge> = maze.edges().collect();
+        for &edge in &edges {
+            if maze.wall(edge) == Some(false) {
+                assert_eq!(maze.set_wall(edge, true), Err(EditError::Disconnected));
+                assert_eq!(maze.grid, grid);
+            }
+        }
+
+        // Opening a wall creates a loop, which the wall can close again
+        let edge = edges
+            .into_iter()
+            .find(|&edge| maze.wall(edge) == Some(true))
+            .unwrap();
+        assert_eq!(maze.toggle_wall(edge), Ok(false));
+        assert_eq!(maze.toggle_wall(edge), Ok(true));
+        assert_eq!(maze.grid, grid);
+    }
+
+    #[test]
+    fn applying_a_diff_gives_the_other_maze() {
+        for topology in [Topology::PLANE, Topology::TORUS, Topology::KLEIN] {
+            let mut maze = build(topology, 2);
+            let other = build(topology, 3);
+            let diff = maze.diff(&other).unwrap();
+            assert!(!diff.is_empty());
+
+            // Open walls first, so that the maze stays connected while the
+            // other walls are added
+            let (openings, walls): (Vec<Edge>, Vec<Edge>) = diff
+                .into_iter()
+                .partition(|&edge| other.wall(edge) == Some(false));
+            for edge in openings {
+                maze.set_wall(edge, false).unwrap();
+            }
+            for edge in walls {
+                maze.set_wall(edge, true).unwrap();
+            }
+            assert_eq!(maze.grid, other.grid, "{:?}", topology);
+            assert_eq!(maze.diff(&other), Ok(Vec::new()));
+        }
+
+        let other = Maze::build(5, 6, WallWeights::from_fn(|_| 1)).unwrap();
+        assert_eq!(
+            build(Topology::PLANE, 2).diff(&other),
+            Err(EditError::Mismatch)
+        );
+    }
+
+    #[test]
+    fn walls_across_a_seam_are_set_on_both_borders() {
+        let (width, height) = (6, 5);
+        for topology in [Topology::TORUS, Topology::MOBIUS] {
+            let mut maze = build(topology, 4);
+            let seams = (0..height)
+                .map(|row| Edge::East(row, width - 1))
+                .chain((0..width).map(|col| Edge::South(height - 1, col)));
+            for edge in seams {
+                // The far side of the seam, on the left or top border
+                let (near, far) = match edge {
+                    Edge::East(row, _) => {
+                        let far_row = match topology.horizontal {
+                            Boundary::Twisted => height - 1 - row,
+                            _ => row,
+                        };
+                        ((row * 2 + 1, width * 2), (far_row * 2 + 1, 0))
+                    }
+                    Edge::South(_, col) => ((height * 2, col * 2 + 1), (0, col * 2 + 1)),
+                };
+                let Some(wall) = maze.wall(edge) else {
+                    assert_eq!(topology.vertical, Boundary::Closed);
+                    assert_eq!(maze.set_wall(edge, false), Err(EditError::InvalidEdge));
+                    continue;
+                };
+                assert_eq!(
+                    (maze.grid[near.0][near.1], maze.grid[far.0][far.1]),
+                    (wall, wall)
+                );
+                if wall {
+                    maze.set_wall(edge, false).unwrap();
+                    assert!(!maze.grid[near.0][near.1] && !maze.grid[far.0][far.1]);
+                    maze.set_wall(edge, true).unwrap();
+                    assert!(maze.grid[near.0][near.1] && maze.grid[far.0][far.1]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::maze::{get_adjacent_cell, Direction, Maze, Move, Topology};
+use crate::solve::is_open;
+
+// What is known about a passage out of a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallState {
+    Unknown,
+    Open,
+    Wall,
+}
+
+// How to treat passages that are not known yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Assumption {
+    // Every unknown passage is open and leads to the cell next to it
+    Optimistic,
+    // Every unknown passage is a wall
+    Pessimistic,
+}
+
+// The walls of a maze as far as they have been revealed. The outline of the
+// maze, including cells left out by a mask, is known from the start.
+#[derive(Debug, Clone)]
+pub struct KnownMap {
+    width: usize,
+    height: usize,
+    // Move out of every cell in each direction, in `Direction::ALL` order.
+    // `None` when unknown, `Some(None)` for a wall.
+    passages: Vec<[Option<Option<Move>>; 4]>,
+    // Cell next to every cell in each direction, whatever the walls
+    adjacent: Vec<[Option<Move>; 4]>,
+    visited: Vec<bool>,
+}
+
+impl KnownMap {
+    // A map of `maze` where nothing has been revealed yet
+    pub fn new(maze: &Maze) -> Self {
+        Self::with_outline(maze.width, maze.height, maze.topology, |cell| {
+            is_open(maze, cell)
+        })
+    }
+
+    // A map of a `width` x `height` maze drawn on `topology` where nothing is
+    // known yet, to be filled in with `set_state`
+    pub fn with_size(width: usize, height: usize, topology: Topology) -> Option<Self> {
+        if width < 2 || height < 2 {
+            return None;
+        }
+        Some(Self::with_outline(width, height, topology, |_| true))
+    }
+
+    fn with_outline<F>(width: usize, height: usize, topology: Topology, is_open: F) -> Self
+    where
+        F: Fn((usize, usize)) -> bool,
+    {
+        let adjacent = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .map(|(row, col)| {
+                Direction::ALL.map(|direction| {
+                    let (to, mirrored) =
+                        get_adjacent_cell(width, height, topology, row, col, direction)?;
+                    if !is_open((row, col)) || !is_open(to) {
+                        return None;
+                    }
+                    Some(Move {
+                        to,
+                        direction,
+                        length: 1,
+                        mirrored,
+                    })
+                })
+            })
+            .collect();
+        KnownMap {
+            width,
+            height,
+            passages: vec![[None; 4]; width * height],
+            adjacent,
+            visited: vec![false; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Reveal the four walls around a cell, as seen from both sides
+    pub fn reveal(&mut self, maze: &Maze, cell: (usize, usize)) {
+        let Some(index) = self.index(cell) else {
+            return;
+        };
+        self.visited[index] = true;
+        for direction in Direction::ALL {
+            self.record(maze, cell, direction);
+        }
+    }
+
+    // Record the passage out of a cell in `direction` together with the way
+    // back, so that the map always knows passages from both ends. Passages
+    // leave and enter in the same direction, so the way back is always the
+    // opposite one.
+    fn record(&mut self, maze: &Maze, cell: (usize, usize), direction: Direction) {
+        let back = direction.opposite();
+        let found = get_move(maze, cell, direction);
+        self.set(cell, direction, found);
+        match found {
+            Some(m) => {
+                self.set(m.to, back, get_move(maze, m.to, back));
+                // A tunnel leaves the crossing above it without a way along it
+                if m.length == 2 {
+                    if let Some(crossing) = self.get_adjacent(cell, direction) {
+                        self.set(crossing.to, direction, None);
+                        self.set(crossing.to, back, None);
+                    }
+                }
+            }
+            None => {
+                let Some(neighbor) = self.get_adjacent(cell, direction) else {
+                    return;
+                };
+                // The neighbor may still tunnel under the cell
+                let tunnel = get_move(maze, neighbor.to, back);
+                self.set(neighbor.to, back, tunnel);
+                if let Some(tunnel) = tunnel {
+                    self.set(tunnel.to, direction, get_move(maze, tunnel.to, direction));
+                }
+            }
+        }
+    }
+
+    // Set what is known about the wall between a cell and the cell next to
+    // it in `direction`, on both sides
+    pub fn set_state(&mut self, cell: (usize, usize), direction: Direction, state: WallState) {
+        let Some(neighbor) = self.get_adjacent(cell, direction) else {
+            return;
+        };
+        let back = direction.opposite();
+        let Some(index) = self.index(cell) else {
+            return;
+        };
+        let neighbor_index = neighbor.to.0 * self.width + neighbor.to.1;
+        let (forward, backward) = match state {
+            WallState::Unknown => (None, None),
+            WallState::Wall => (Some(None), Some(None)),
+            WallState::Open => (
+                Some(Some(neighbor)),
+                Some(self.get_adjacent(neighbor.to, back)),
+            ),
+        };
+        self.passages[index][direction as usize] = forward;
+        self.passages[neighbor_index][back as usize] = backward;
+    }
+
+    // Bounds on the length of the shortest route from `from` to `to`, and
+    // whether the best known route is proven to be the shortest
+    pub fn bounds(&self, from: (usize, usize), to: (usize, usize)) -> PathBounds {
+        let optimistic = self.distance(from, to, Assumption::Optimistic);
+        let pessimistic = self.distance(from, to, Assumption::Pessimistic);
+        PathBounds {
+            optimistic,
+            pessimistic,
+            best_path: self.shortest_path(from, to, Assumption::Pessimistic),
+            proven: pessimistic.is_some() && pessimistic == optimistic,
+        }
+    }
+
+    fn distance(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        assumption: Assumption,
+    ) -> Option<u32> {
+        let index = self.index(to)?;
+        self.distances(from, assumption)[index]
+    }
+
+    fn set(&mut self, cell: (usize, usize), direction: Direction, state: Option<Move>) {
+        if let Some(index) = self.index(cell) {
+            self.passages[index][direction as usize] = Some(state);
+        }
+    }
+
+    fn get_adjacent(&self, cell: (usize, usize), direction: Direction) -> Option<Move> {
+        self.adjacent[self.index(cell)?][direction as usize]
+    }
+
+    pub fn is_visited(&self, cell: (usize, usize)) -> bool {
+        self.index(cell).is_some_and(|index| self.visited[index])
+    }
+
+    // Number of cells visited so far
+    pub fn visited_count(&self) -> usize {
+        self.visited.iter().filter(|&&visited| visited).count()
+    }
+
+    pub fn state(&self, cell: (usize, usize), direction: Direction) -> WallState {
+        match self
+            .index(cell)
+            .map(|index| self.passages[index][direction as usize])
+        {
+            Some(Some(Some(_))) => WallState::Open,
+            Some(Some(None)) => WallState::Wall,
+            _ => WallState::Unknown,
+        }
+    }
+
+    // Moves out of a cell, treating unknown passages according to `assumption`
+    pub fn moves(&self, cell: (usize, usize), assumption: Assumption) -> Vec<Move> {
+        let Some(index) = self.index(cell) else {
+            return Vec::new();
+        };
+        (0..4)
+            .filter_map(|i| match self.passages[index][i] {
+                Some(known) => known,
+                None if assumption == Assumption::Optimistic => self.adjacent[index][i],
+                None => None,
+            })
+            .collect()
+    }
+
+    // Number of cells travelled from `from` to every cell, `None` where
+    // unreachable, treating unknown passages according to `assumption`
+    pub fn distances(&self, from: (usize, usize), assumption: Assumption) -> Vec<Option<u32>> {
+        self.search(from, assumption)
+            .into_iter()
+            .map(|(distance, _)| distance)
+            .collect()
+    }
+
+    // Cells along a shortest route from `from` to `to`, both included
+    pub fn shortest_path(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        assumption: Assumption,
+    ) -> Option<Vec<(usize, usize)>> {
+        let target = self.index(to)?;
+        let found = self.search(from, assumption);
+        found[target].0?;
+        let mut path = vec![to];
+        let mut index = target;
+        while let Some(parent) = found[index].1 {
+            path.push((parent / self.width, parent % self.width));
+            index = parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Distance to every cell and the cell it is reached from
+    fn search(
+        &self,
+        from: (usize, usize),
+        assumption: Assumption,
+    ) -> Vec<(Option<u32>, Option<usize>)> {
+        let mut found = vec![(None, None); self.width * self.height];
+        let Some(start) = self.index(from) else {
+            return found;
+        };
+        let mut queue = BinaryHeap::from([Reverse((0, start))]);
+        found[start].0 = Some(0);
+        while let Some(Reverse((distance, index))) = queue.pop() {
+            if found[index].0.is_some_and(|best| best < distance) {
+                continue;
+            }
+            let cell = (index / self.width, index % self.width);
+            for m in self.moves(cell, assumption) {
+                let next = m.to.0 * self.width + m.to.1;
+                let next_distance = distance + m.length;
+                if found[next].0.is_none_or(|best| next_distance < best) {
+                    found[next] = (Some(next_distance), Some(index));
+                    queue.push(Reverse((next_distance, next)));
+                }
+            }
+        }
+        found
+    }
+
+    fn index(&self, (row, col): (usize, usize)) -> Option<usize> {
+        (row < self.height && col < self.width).then_some(row * self.width + col)
+    }
+}
+
+fn get_move(maze: &Maze, (row, col): (usize, usize), direction: Direction) -> Option<Move> {
+    maze.moves(row, col)
+        .into_iter()
+        .find(|m| m.direction == direction)
+}
+
+// What a partially known map says about the shortest route between two cells,
+// counted in cells travelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathBounds {
+    // Length of the shortest route if every unknown passage were open. No
+    // route in the maze can be shorter. `None` when no route is possible.
+    pub optimistic: Option<u32>,
+    // Length of the shortest route through known passages, `None` when no
+    // route is known yet
+    pub pessimistic: Option<u32>,
+    // Cells along the shortest route through known passages
+    pub best_path: Option<Vec<(usize, usize)>>,
+    // Whether no unknown passage could lead to a shorter route than the best
+    // known one
+    pub proven: bool,
+}
+
+// A mouse in a maze it cannot see, which reveals the walls around every cell
+// it enters
+#[derive(Debug, Clone)]
+pub struct Simulator<'a> {
+    maze: &'a Maze,
+    map: KnownMap,
+    position: (usize, usize),
+    steps: usize,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(maze: &'a Maze, start: (usize, usize)) -> Self {
+        let mut map = KnownMap::new(maze);
+        map.reveal(maze, start);
+        Simulator {
+            maze,
+            map,
+            position: start,
+            steps: 0,
+        }
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    pub fn map(&self) -> &KnownMap {
+        &self.map
+    }
+
+    // Number of moves made so far
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // Move in `direction` if the way is open. Returns whether the mouse moved.
+    pub fn step(&mut self, direction: Direction) -> bool {
+        let Some(m) = get_move(self.maze, self.position, direction) else {
+            return false;
+        };
+        self.position = m.to;
+        self.steps += 1;
+        self.map.reveal(self.maze, m.to);
+        true
+    }
+
+    // Bounds on the shortest route between two cells given what the mouse
+    // has seen so far
+    pub fn bounds(&self, from: (usize, usize), to: (usize, usize)) -> PathBounds {
+        self.map.bounds(from, to)
+    }
+
+    // Head for `target` along the shortest route the map allows, assuming
+    // unknown passages are open and revising the route as walls show up.
+    // Returns whether the target was reached.
+    fn flood_to(&mut self, target: (usize, usize), limit: usize) -> bool {
+        while self.position != target && self.steps < limit {
+            let distances = self.map.distances(target, Assumption::Optimistic);
+            let distance = |cell: (usize, usize)| distances[cell.0 * self.map.width + cell.1];
+            // The current cell has been revealed, so every move out of it is known
+            let best = self
+                .map
+                .moves(self.position, Assumption::Pessimistic)
+                .into_iter()
+                .filter_map(|m| Some((distance(m.to)? + m.length, m.direction)))
+                .min_by_key(|&(cost, _)| cost);
+            match best {
+                Some((_, direction)) => {
+                    self.step(direction);
+                }
+                None => return false,
+            }
+        }
+        self.position == target
+    }
+
+    // Follow known passages to `target`. Returns whether the target was reached.
+    fn travel_to(&mut self, target: (usize, usize)) -> bool {
+        let Some(path) = self
+            .map
+            .shortest_path(self.position, target, Assumption::Pessimistic)
+        else {
+            return false;
+        };
+        for &next in &path[1..] {
+            let found = self
+                .map
+                .moves(self.position, Assumption::Pessimistic)
+                .into_iter()
+                .find(|m| m.to == next);
+            match found {
+                Some(m) => {
+                    self.step(m.direction);
+                }
+                None => return false,
+            }
+        }
+        self.position == target
+    }
+}
+
+// ========== Exploration Policies ==========
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    // Head straight for the goal, assuming unknown passages are open
+    FloodFill,
+    // Head for the goal, then keep visiting cells on the shortest route the
+    // unknown passages could allow until the route is proven
+    ProveShortest,
+    // Always visit the nearest unvisited cell until the goal is found
+    Frontier,
+    // Head for the goal, then back to the start the same way, exploring new
+    // cells that may prove a shorter route instead of retracing known
+    // passages
+    ReturnToStart,
+}
+
+// Outcome of exploring a maze
+#[derive(Debug, Clone)]
+pub struct Exploration {
+    // Number of moves made
+    pub steps: usize,
+    // Number of cells entered at least once, including the start
+    pub cells_visited: usize,
+    pub reached_goal: bool,
+    // Whether the shortest known route to the goal is known to be the
+    // shortest one in the maze
+    pub proven: bool,
+    // Number of cells travelled along the shortest known route to the goal
+    pub known_distance: Option<u32>,
+    // Where the mouse ended up
+    pub position: (usize, usize),
+    // Everything revealed along the way
+    pub map: KnownMap,
+}
+
+// Let a mouse find its way from `start` to `goal` without knowing the maze
+pub fn explore(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    policy: Policy,
+) -> Exploration {
+    let mut mouse = Simulator::new(maze, start);
+    // Every policy reaches its targets well within this many moves
+    let limit = maze.width * maze.height * 64;
+
+    let reached_goal = is_open(maze, start)
+        && is_open(maze, goal)
+        && match policy {
+            Policy::FloodFill => mouse.flood_to(goal, limit),
+            Policy::ProveShortest => {
+                let reached = mouse.flood_to(goal, limit);
+                while reached && !mouse.bounds(start, goal).proven && mouse.steps < limit {
+                    let Some(target) = nearest_unproven_cell(&mouse, start, goal) else {
+                        break;
+                    };
+                    mouse.flood_to(target, limit);
+                }
+                reached
+            }
+            Policy::Frontier => {
+                while !mouse.map.is_visited(goal) && mouse.steps < limit {
+                    let Some(target) = nearest_frontier_cell(&mouse) else {
+                        break;
+                    };
+                    mouse.travel_to(target);
+                }
+                mouse.map.is_visited(goal) && mouse.travel_to(goal)
+            }
+            Policy::ReturnToStart => mouse.flood_to(goal, limit) && mouse.flood_to(start, limit),
+        };
+
+    let bounds = mouse.bounds(start, goal);
+    Exploration {
+        steps: mouse.steps,
+        cells_visited: mouse.map.visited_count(),
+        reached_goal,
+        proven: bounds.proven,
+        known_distance: bounds.pessimistic,
+        position: mouse.position,
+        map: mouse.map,
+    }
+}
+
+// The unvisited cell nearest to the mouse on the shortest route from `start`
+// to `goal` that the unknown passages could allow
+fn nearest_unproven_cell(
+    mouse: &Simulator,
+    start: (usize, usize),
+    goal: (usize, usize),
+) -> Option<(usize, usize)> {
+    let path = mouse
+        .map
+        .shortest_path(start, goal, Assumption::Optimistic)?;
+    let distances = mouse.map.distances(mouse.position, Assumption::Optimistic);
+    path.into_iter()
+        .filter(|&cell| !mouse.map.is_visited(cell))
+        .min_by_key(|cell| distances[cell.0 * mouse.map.width + cell.1].unwrap_or(u32::MAX))
+}
+
+// The nearest unvisited cell the mouse knows how to get to
+fn nearest_frontier_cell(mouse: &Simulator) -> Option<(usize, usize)> {
+    let distances = mouse.map.distances(mouse.position, Assumption::Pessimistic);
+    distances
+        .iter()
+        .enumerate()
+        .filter_map(|(index, distance)| Some((distance.as_ref()?, index)))
+        .map(|(&distance, index)| (distance, (index / mouse.map.width, index % mouse.map.width)))
+        .filter(|&(_, cell)| !mouse.map.is_visited(cell))
+        .min()
+        .map(|(_, cell)| cell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildOptions, WallWeights};
+    use crate::solve;
+
+    #[test]
+    fn every_policy_reaches_the_goal() {
+        let policies = [
+            Policy::FloodFill,
+            Policy::ProveShortest,
+            Policy::Frontier,
+            Policy::ReturnToStart,
+        ];
+        for topology in [Topology::PLANE, Topology::TORUS] {
+            for seed in 0..5 {
+                let options = BuildOptions {
+                    topology,
+                    seed: Some(seed),
+                    ..Default::default()
+                };
+                let maze =
+                    Maze::build_with_options(9, 7, WallWeights::from_fn(|_| 1), options).unwrap();
+                let (start, goal) = ((0, 0), (6, 8));
+                let shortest = solve::bfs(&maze, start, goal).unwrap().cost;
+                for policy in policies {
+                    let run = explore(&maze, start, goal, policy);
+                    assert!(run.reached_goal, "{:?}", policy);
+                    // Proving the route may take the mouse anywhere
+                    match policy {
+                        Policy::ReturnToStart => assert_eq!(run.position, start),
+                        Policy::ProveShortest => {}
+                        _ => assert_eq!(run.position, goal, "{:?}", policy),
+                    }
+                    assert!(run.steps >= shortest as usize);
+                    // A maze without loops has a single route, known once
+                    // the goal is reached
+                    assert_eq!(run.known_distance, Some(shortest), "{:?}", policy);
+                }
+                let run = explore(&maze, start, goal, Policy::ProveShortest);
+                assert!(run.proven);
+            }
+        }
+    }
+}
@@ -34,6 +34,11 @@ where
         self.tree.len()
     }
 
+    // Whether the Fenwick tree holds no values
+    pub fn is_empty(&self) -> bool {
+        self.tree.len() <= 1
+    }
+
     // Add: update the value at `i` by `delta`
     pub fn set(&mut self, mut i: usize, value: I) -> Result<(), GenericError> {
         let size = self.len();
@@ -75,7 +80,7 @@ where
     }
 
     pub fn get_final_sum(&self) -> I {
-        return self.final_sum;
+        self.final_sum
     }
 
     // Get cumulative sum up to `i`
@@ -132,11 +137,7 @@ where
     // Get cumulative sums
     pub fn get_sums(&self) -> Result<Vec<I>, GenericError> {
         let size = self.len();
-        let mut res = vec![I::default(); size - 1];
-        for i in 0..size - 1 {
-            res[i] = self.get_sum(i)?;
-        }
-        Ok(res)
+        (0..size - 1).map(|i| self.get_sum(i)).collect()
     }
 }
 
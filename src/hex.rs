@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use disjoint_sets::UnionFind;
+
+use crate::kruskal::{self, WallGraph};
+
+// Cells are flat-topped hexagons laid out in columns, with every odd column
+// shifted down by half a cell:
+//  __    __
+// /  \__/  \__
+// \__/  \__/  \
+// /  \__/  \__/
+// \__/  \__/  \
+//    \__/  \__/
+#[derive(Debug)]
+pub struct HexMaze {
+    pub width: usize,
+    pub height: usize,
+    // Wall state of every edge, `true` for a wall
+    walls: Vec<bool>,
+    layout: HexLayout,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexDirection {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::North,
+        HexDirection::NorthEast,
+        HexDirection::SouthEast,
+        HexDirection::South,
+        HexDirection::SouthWest,
+        HexDirection::NorthWest,
+    ];
+}
+
+impl HexMaze {
+    pub fn build(width: usize, height: usize, config: HexWallWeights) -> Option<HexMaze> {
+        if width < 2 || height < 2 {
+            return None;
+        }
+
+        let layout = HexLayout::new(width, height);
+        let number_of_edges = layout.edge_cells.len();
+        let mut walls = vec![true; number_of_edges];
+        let mut cells = UnionFind::<usize>::new(width * height);
+
+        // Only edges between two cells can be removed
+        let candidates: Vec<bool> = layout
+            .edge_cells
+            .iter()
+            .map(|(a, b)| a.is_some() && b.is_some())
+            .collect();
+        let graph = HexGrid {
+            layout: &layout,
+            config: &config,
+        };
+        kruskal::carve(
+            &graph,
+            &mut walls,
+            &mut cells,
+            &candidates,
+            &mut rand::thread_rng(),
+            &mut |_, _| {},
+        )?;
+
+        // Walls with a weight of 0 are never removed, which may leave cells
+        // out of reach
+        if (1..width * height).any(|cell| !cells.equiv(0, cell)) {
+            return None;
+        }
+
+        Some(HexMaze {
+            width,
+            height,
+            walls,
+            layout,
+        })
+    }
+
+    // Whether the given side of the cell at (`row`, `col`) is a wall
+    pub fn has_wall(&self, row: usize, col: usize, direction: HexDirection) -> bool {
+        if row >= self.height || col >= self.width {
+            return true;
+        }
+        self.walls[self.layout.cell_edges[row * self.width + col][direction as usize]]
+    }
+
+    // The cell on the given side of the cell at (`row`, `col`), if any
+    pub fn neighbor(
+        &self,
+        row: usize,
+        col: usize,
+        direction: HexDirection,
+    ) -> Option<(usize, usize)> {
+        if row >= self.height || col >= self.width {
+            return None;
+        }
+        let cell = row * self.width + col;
+        let id = self.layout.cell_edges[cell][direction as usize];
+        let other = match self.layout.edge_cells[id] {
+            (Some(a), Some(b)) if a == cell => b,
+            (Some(a), Some(_)) => a,
+            _ => return None,
+        };
+        Some((other / self.width, other % self.width))
+    }
+
+    // Render the maze using `/`, `\` and `__` for the walls
+    pub fn to_ascii(&self) -> String {
+        let rows = self.height * 2 + 2;
+        let cols = self.width * 3 + 1;
+        let mut canvas = vec![vec![' '; cols]; rows];
+
+        for (id, &((x1, y1), (_, y2))) in self.layout.edge_vertices.iter().enumerate() {
+            if !self.walls[id] {
+                continue;
+            }
+            if y1 == y2 {
+                canvas[y1][x1] = '_';
+                canvas[y1][x1 + 1] = '_';
+            } else if y2 > y1 {
+                canvas[y2][x1] = '\\';
+            } else {
+                canvas[y1][x1] = '/';
+            }
+        }
+
+        let mut res = String::with_capacity(rows * (cols + 1));
+        for row in canvas {
+            let line: String = row.into_iter().collect();
+            res.push_str(line.trim_end());
+            res.push('\n');
+        }
+        res
+    }
+
+    pub fn print(&self) {
+        print!("{}", self.to_ascii());
+    }
+
+    // Render the maze as an SVG image where each cell is `cell_size` wide
+    pub fn to_svg(&self, cell_size: f64) -> String {
+        let unit_x = cell_size / 4.0;
+        let unit_y = cell_size * 3f64.sqrt() / 4.0;
+        let margin = cell_size / 4.0;
+        let image_width = (self.width * 3 + 1) as f64 * unit_x + margin * 2.0;
+        let image_height = (self.height * 2 + 1) as f64 * unit_y + margin * 2.0;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.1}" height="{:.1}">"#,
+            image_width, image_height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<g stroke="black" stroke-width="{:.1}" stroke-linecap="round">"#,
+            cell_size / 10.0
+        );
+        for (id, &((x1, y1), (x2, y2))) in self.layout.edge_vertices.iter().enumerate() {
+            if !self.walls[id] {
+                continue;
+            }
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/>"#,
+                margin + x1 as f64 * unit_x,
+                margin + y1 as f64 * unit_y,
+                margin + x2 as f64 * unit_x,
+                margin + y2 as f64 * unit_y
+            );
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+// ========== Hex Geometry ==========
+
+// Corners are addressed by (x, y) on a lattice where a cell is 4 units wide
+// and 2 units tall. The cell at (row, col) has its top-left corner at
+// (3 * col + 1, 2 * row + col % 2).
+type Corner = (usize, usize);
+
+#[derive(Debug)]
+struct HexLayout {
+    // Corners at both ends of each edge, ordered by x
+    edge_vertices: Vec<(Corner, Corner)>,
+    // Cells on either side of each edge. Border edges have a single cell.
+    edge_cells: Vec<(Option<usize>, Option<usize>)>,
+    // Edge on each side of each cell, indexed by `HexDirection`
+    cell_edges: Vec<[usize; 6]>,
+    // Edges meeting at each corner
+    corner_edges: HashMap<Corner, Vec<usize>>,
+}
+
+impl HexLayout {
+    fn new(width: usize, height: usize) -> Self {
+        let mut layout = HexLayout {
+            edge_vertices: Vec::new(),
+            edge_cells: Vec::new(),
+            cell_edges: Vec::with_capacity(width * height),
+            corner_edges: HashMap::new(),
+        };
+        let mut edge_ids: HashMap<(Corner, Corner), usize> = HashMap::new();
+
+        for row in 0..height {
+            for col in 0..width {
+                let cell = row * width + col;
+                let (x, y) = (3 * col, 2 * row + col % 2);
+                let left = (x, y + 1);
+                let top_left = (x + 1, y);
+                let top_right = (x + 3, y);
+                let right = (x + 4, y + 1);
+                let bottom_right = (x + 3, y + 2);
+                let bottom_left = (x + 1, y + 2);
+                let sides = [
+                    (top_left, top_right),
+                    (top_right, right),
+                    (bottom_right, right),
+                    (bottom_left, bottom_right),
+                    (left, bottom_left),
+                    (left, top_left),
+                ];
+
+                let mut edges = [0; 6];
+                for (edge, side) in edges.iter_mut().zip(sides) {
+                    *edge = match edge_ids.get(&side) {
+                        Some(&id) => {
+                            layout.edge_cells[id].1 = Some(cell);
+                            id
+                        }
+                        None => {
+                            let id = layout.edge_vertices.len();
+                            edge_ids.insert(side, id);
+                            layout.edge_vertices.push(side);
+                            layout.edge_cells.push((Some(cell), None));
+                            layout.corner_edges.entry(side.0).or_default().push(id);
+                            layout.corner_edges.entry(side.1).or_default().push(id);
+                            id
+                        }
+                    };
+                }
+                layout.cell_edges.push(edges);
+            }
+        }
+
+        layout
+    }
+
+    // The other edges meeting the given edge at a corner, as (edge on the
+    // side of cell A, edge on the side of cell B)
+    fn corner_neighbors(&self, id: usize, corner: Corner) -> Option<(usize, usize)> {
+        let (cell_a, cell_b) = self.edge_cells[id];
+        let others = self.corner_edges.get(&corner)?;
+        let find = |cell| {
+            others.iter().copied().find(|&other| {
+                other != id && {
+                    let (a, b) = self.edge_cells[other];
+                    a == cell || b == cell
+                }
+            })
+        };
+        Some((find(cell_a)?, find(cell_b)?))
+    }
+}
+
+struct HexGrid<'a> {
+    layout: &'a HexLayout,
+    config: &'a HexWallWeights,
+}
+
+impl WallGraph for HexGrid<'_> {
+    fn edge_count(&self) -> usize {
+        self.layout.edge_cells.len()
+    }
+
+    fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)> {
+        match self.layout.edge_cells[id] {
+            (Some(a), Some(b)) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    fn neighbor_edges(&self, id: usize) -> Vec<usize> {
+        let (u, v) = self.layout.edge_vertices[id];
+        [u, v]
+            .iter()
+            .filter_map(|corner| self.layout.corner_edges.get(corner))
+            .flatten()
+            .copied()
+            .filter(|&other| other != id)
+            .collect()
+    }
+
+    fn weight(&self, edges: &[bool], id: usize) -> Option<u32> {
+        Some(self.config.get(get_hex_wall_type(self.layout, edges, id)?))
+    }
+}
+
+// ========== Hex Wall Weights ==========
+
+pub struct HexWallWeights {
+    // Name Format: type_[0][1]x[2][3]
+    // Each end of the wall `~` between cells A and B meets one other wall of
+    // A and one other wall of B. Walls that mirror each other along or
+    // across `~` are considered to be of the same type.
+    //  0         2
+    //   \   A   /
+    //    ---~---
+    //   /   B   \
+    //  1         3
+    pub type_11x11: u32,
+
+    //   \     /
+    //    --~--
+    //   /
+    pub type_11x10: u32,
+
+    //   \
+    //    --~--
+    //   /
+    pub type_11x00: u32,
+
+    //   \     /
+    //    --~--
+    //
+    pub type_10x10: u32,
+
+    //   \
+    //    --~--
+    //         \
+    pub type_10x01: u32,
+
+    //   \
+    //    --~--
+    //
+    pub type_10x00: u32,
+
+    //
+    //    --~--
+    //
+    pub type_00x00: u32,
+}
+
+impl HexWallWeights {
+    // Weight of the given wall type
+    pub fn get(&self, wall_type: HexWallType) -> u32 {
+        match wall_type {
+            HexWallType::Type11x11 => self.type_11x11,
+            HexWallType::Type11x10 => self.type_11x10,
+            HexWallType::Type11x00 => self.type_11x00,
+            HexWallType::Type10x10 => self.type_10x10,
+            HexWallType::Type10x01 => self.type_10x01,
+            HexWallType::Type10x00 => self.type_10x00,
+            HexWallType::Type00x00 => self.type_00x00,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexWallType {
+    Type11x11,
+    Type11x10,
+    Type11x00,
+    Type10x10,
+    Type10x01,
+    Type10x00,
+    Type00x00,
+}
+
+impl HexWallType {
+    // Classify the neighboring walls of an edge, given in the order of the
+    // `HexWallWeights` diagram
+    pub fn from_neighbors(neighbors: [bool; 4]) -> HexWallType {
+        let [a, b, c, d] = neighbors;
+        let mask = |a: bool, b: bool, c: bool, d: bool| {
+            (u8::from(a) << 3) | (u8::from(b) << 2) | (u8::from(c) << 1) | u8::from(d)
+        };
+        // Swapping the ends and mirroring along the wall give the same type
+        let canonical = [
+            mask(a, b, c, d),
+            mask(c, d, a, b),
+            mask(b, a, d, c),
+            mask(d, c, b, a),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or_default();
+
+        match canonical {
+            0b1111 => HexWallType::Type11x11,
+            0b1110 => HexWallType::Type11x10,
+            0b1100 => HexWallType::Type11x00,
+            0b1010 => HexWallType::Type10x10,
+            0b1001 => HexWallType::Type10x01,
+            0b1000 => HexWallType::Type10x00,
+            _ => HexWallType::Type00x00,
+        }
+    }
+}
+
+fn get_hex_wall_type(layout: &HexLayout, edges: &[bool], id: usize) -> Option<HexWallType> {
+    let (u, v) = layout.edge_vertices[id];
+    let (u_a, u_b) = layout.corner_neighbors(id, u)?;
+    let (v_a, v_b) = layout.corner_neighbors(id, v)?;
+    Some(HexWallType::from_neighbors([
+        edges[u_a], edges[u_b], edges[v_a], edges[v_b],
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn weights(weight: u32) -> HexWallWeights {
+        HexWallWeights {
+            type_11x11: weight,
+            type_11x10: weight,
+            type_11x00: weight,
+            type_10x10: weight,
+            type_10x01: weight,
+            type_10x00: weight,
+            type_00x00: weight,
+        }
+    }
+
+    #[test]
+    fn mazes_are_spanning_trees() {
+        for (width, height) in [(2, 2), (5, 4), (8, 9)] {
+            let maze = HexMaze::build(width, height, weights(1)).unwrap();
+            let mut reached = HashSet::from([(0, 0)]);
+            let mut stack = vec![(0, 0)];
+            let mut openings = 0;
+            while let Some((row, col)) = stack.pop() {
+                for direction in HexDirection::ALL {
+                    if maze.has_wall(row, col, direction) {
+                        continue;
+                    }
+                    openings += 1;
+                    let next = maze.neighbor(row, col, direction).unwrap();
+                    if reached.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            assert_eq!(reached.len(), width * height);
+            // Every opening is seen from both of its cells
+            assert_eq!(openings / 2, width * height - 1);
+        }
+    }
+
+    #[test]
+    fn mazes_without_weights_are_rejected() {
+        assert!(HexMaze::build(4, 3, weights(0)).is_none());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::maze::Maze;
+
+// Red, green and blue components of a pixel
+pub type Rgb = [u8; 3];
+
+// Settings for `Maze::to_image`
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    // Pixels across every cell
+    pub cell_size: usize,
+    // Pixels across every wall
+    pub wall_size: usize,
+    pub wall_color: Rgb,
+    // Colour of open cells without a value
+    pub background: Rgb,
+    // Value of every cell, indexed by `row * width + col`, such as its
+    // distance from the start or how often it was visited. Cells are
+    // coloured from `low_color` for the smallest value to `high_color` for
+    // the largest one.
+    pub field: Option<Vec<Option<f64>>>,
+    pub low_color: Rgb,
+    pub high_color: Rgb,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            cell_size: 8,
+            wall_size: 2,
+            wall_color: [0, 0, 0],
+            background: [255, 255, 255],
+            field: None,
+            low_color: [255, 255, 204],
+            high_color: [189, 0, 38],
+        }
+    }
+}
+
+// An image stored row by row, top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    // Encode the image as a binary PPM file
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut res = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        res.extend(self.pixels.iter().flatten());
+        res
+    }
+
+    // Encode the image as a PNG file
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut res = Vec::new();
+        let mut encoder = png::Encoder::new(&mut res, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels.concat())?;
+        writer.finish()?;
+        Ok(res)
+    }
+}
+
+impl Maze {
+    // Draw the maze as an image. Open walls between two cells take the
+    // average colour of both cells.
+    pub fn to_image(&self, options: &ImageOptions) -> Image {
+        let colors = self.get_cell_colors(options);
+        let cell_color = |i: usize, j: usize| {
+            if self.grid[i][j] {
+                options.wall_color
+            } else {
+                colors[(i / 2) * self.width + j / 2]
+            }
+        };
+
+        // Size of every raster row and column, alternating walls and cells
+        let size = |k: usize| {
+            if k % 2 == 1 {
+                options.cell_size
+            } else {
+                options.wall_size
+            }
+        };
+        let rows = &self.grid;
+        let cols = self.width * 2 + 1;
+        let width = (0..cols).map(size).sum();
+        let height = (0..rows.len()).map(size).sum();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for (i, row) in rows.iter().enumerate() {
+            let mut line = Vec::with_capacity(width);
+            for (j, &wall) in row.iter().enumerate() {
+                let color = match (wall, i % 2, j % 2) {
+                    (true, _, _) => options.wall_color,
+                    (false, 1, 1) => cell_color(i, j),
+                    (false, 1, _) => {
+                        let sides = [j.checked_sub(1), Some(j + 1).filter(|&j| j < cols)];
+                        average(sides.iter().flatten().map(|&j| cell_color(i, j)))
+                    }
+                    (false, _, _) => {
+                        let sides = [i.checked_sub(1), Some(i + 1).filter(|&i| i < rows.len())];
+                        average(sides.iter().flatten().map(|&i| cell_color(i, j)))
+                    }
+                };
+                line.extend(std::iter::repeat_n(color, size(j)));
+            }
+            for _ in 0..size(i) {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Colour of every cell, ignoring walls
+    fn get_cell_colors(&self, options: &ImageOptions) -> Vec<Rgb> {
+        let cell_count = self.width * self.height;
+        let Some(field) = &options.field else {
+            return vec![options.background; cell_count];
+        };
+        let values = field.iter().flatten().copied().filter(|v| v.is_finite());
+        let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| {
+            (low.min(v), high.max(v))
+        });
+        (0..cell_count)
+            .map(|id| match field.get(id).copied().flatten() {
+                Some(value) if value.is_finite() => {
+                    let t = if high > low {
+                        (value - low) / (high - low)
+                    } else {
+                        0.0
+                    };
+                    mix(options.low_color, options.high_color, t)
+                }
+                _ => options.background,
+            })
+            .collect()
+    }
+}
+
+fn mix(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    [0, 1, 2].map(|k| (a[k] as f64 + (b[k] as f64 - a[k] as f64) * t).round() as u8)
+}
+
+fn average(colors: impl Iterator<Item = Rgb>) -> Rgb {
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for color in colors {
+        for k in 0..3 {
+            sum[k] += color[k] as u32;
+        }
+        count += 1;
+    }
+    sum.map(|s| (s / count.max(1)) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::WallWeights;
+
+    #[test]
+    fn images_cover_every_cell_and_wall() {
+        let maze = Maze::build(5, 3, WallWeights::from_fn(|_| 1)).unwrap();
+        let options = ImageOptions {
+            cell_size: 4,
+            wall_size: 1,
+            ..Default::default()
+        };
+        let image = maze.to_image(&options);
+        assert_eq!((image.width, image.height), (6 + 5 * 4, 4 + 3 * 4));
+        assert_eq!(image.pixels.len(), image.width * image.height);
+        // The top left corner is a post, next to the first cell
+        assert_eq!(image.pixels[0], options.wall_color);
+        assert_eq!(image.pixels[image.width + 1], options.background);
+
+        let ppm = image.to_ppm();
+        let header = format!("P6\n{} {}\n255\n", image.width, image.height);
+        assert!(ppm.starts_with(header.as_bytes()));
+        assert_eq!(ppm.len(), header.len() + image.pixels.len() * 3);
+    }
+}
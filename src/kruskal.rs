@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+
+use disjoint_sets::UnionFind;
+use rand::{Rng, RngCore};
+
+use crate::fenwick_tree::FenwickTree;
+
+// A set of cells separated by walls, where the chance of removing a wall
+// depends on the walls around it
+pub(crate) trait WallGraph {
+    // Number of edges (walls) in the graph
+    fn edge_count(&self) -> usize;
+
+    // Cells on either side of an edge
+    fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)>;
+
+    // Edges whose weight may change when the given edge is removed
+    fn neighbor_edges(&self, id: usize) -> Vec<usize>;
+
+    // Weight of an edge given the current walls
+    fn weight(&self, edges: &[bool], id: usize) -> Option<u32>;
+}
+
+// Progress reported by `carve` as it goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CarveEvent {
+    // An edge was drawn with the given weight, before it is removed or kept
+    Considered { id: usize, weight: u32 },
+    Removed { id: usize },
+    // An edge was kept because removing it would create a loop
+    RejectedCycle { id: usize },
+}
+
+// Remove walls in weighted random order, skipping any wall whose removal
+// would create a loop. `edges` holds the current walls (`true` for a wall),
+// `cells` the cells already joined and `candidates` the edges that may be
+// removed. Every candidate is considered exactly once, drawn with `rng`, and
+// `on_event` is called with the current walls at every step.
+pub(crate) fn carve<G: WallGraph>(
+    graph: &G,
+    edges: &mut [bool],
+    cells: &mut UnionFind<usize>,
+    candidates: &[bool],
+    rng: &mut dyn RngCore,
+    on_event: &mut dyn FnMut(&[bool], CarveEvent),
+) -> Option<()> {
+    let number_of_edges = graph.edge_count();
+    let mut weights = FenwickTree::<u32>::with_len(number_of_edges);
+    let mut seen_edges = HashSet::new();
+
+    // Initialize weight of every edge
+    for (i, &candidate) in candidates.iter().enumerate() {
+        if !candidate {
+            seen_edges.insert(i);
+            continue;
+        }
+        let _ = weights.set(i, graph.weight(edges, i)?);
+    }
+
+    while weights.get_final_sum() > 0 {
+        // Select and set weight of random edge to 0
+        let rand_num = rng.gen_range(1..=weights.get_final_sum());
+        let edge_id_to_remove = weights.get_lower(rand_num).ok()?;
+        let weight = weights.get_value(edge_id_to_remove).ok()?;
+        on_event(
+            edges,
+            CarveEvent::Considered {
+                id: edge_id_to_remove,
+                weight,
+            },
+        );
+        let _ = weights.set(edge_id_to_remove, 0);
+        seen_edges.insert(edge_id_to_remove);
+
+        // Determine if edge should be removed by looking at adjacent cells
+        let (cell_a, cell_b) = graph.adjacent_cells(edge_id_to_remove)?;
+        if !cells.union(cell_a, cell_b) {
+            on_event(
+                edges,
+                CarveEvent::RejectedCycle {
+                    id: edge_id_to_remove,
+                },
+            );
+            continue;
+        }
+        edges[edge_id_to_remove] = false;
+        on_event(
+            edges,
+            CarveEvent::Removed {
+                id: edge_id_to_remove,
+            },
+        );
+
+        // Update weight of each neighbor if the neighbor has not been processed yet
+        for id in graph.neighbor_edges(edge_id_to_remove) {
+            if !seen_edges.contains(&id) {
+                let _ = weights.set(id, graph.weight(edges, id)?);
+            }
+        }
+    }
+
+    Some(())
+}
+
+// Remove walls in weighted random order like `carve`, but a whole orbit of
+// walls at a time so that the result keeps the symmetry the orbits come
+// from. The weight of an orbit is the weight of its first edge. An orbit is
+// only removed if none of its walls would create a loop, and at most one of
+// the orbits flagged in `exclusive` is removed. When that leaves the cells
+// split into pieces, because no symmetric tree exists, the remaining orbits
+// joining two pieces are removed with as few loops as possible. Returns the
+// number of loops created.
+pub(crate) fn carve_symmetric<G: WallGraph>(
+    graph: &G,
+    edges: &mut [bool],
+    cells: &mut UnionFind<usize>,
+    orbits: &[Vec<usize>],
+    exclusive: &[bool],
+    rng: &mut dyn RngCore,
+    on_event: &mut dyn FnMut(&[bool], CarveEvent),
+) -> Option<usize> {
+    let mut orbit_of = HashMap::new();
+    for (k, orbit) in orbits.iter().enumerate() {
+        for &id in orbit {
+            orbit_of.insert(id, k);
+        }
+    }
+    let mut weights = FenwickTree::<u32>::with_len(orbits.len());
+    let mut seen_orbits = HashSet::new();
+    for (k, orbit) in orbits.iter().enumerate() {
+        let _ = weights.set(k, graph.weight(edges, orbit[0])?);
+    }
+
+    let mut exclusive_removed = false;
+    let mut rejected = Vec::new();
+    while weights.get_final_sum() > 0 {
+        let rand_num = rng.gen_range(1..=weights.get_final_sum());
+        let k = weights.get_lower(rand_num).ok()?;
+        let weight = weights.get_value(k).ok()?;
+        let orbit = &orbits[k];
+        on_event(
+            edges,
+            CarveEvent::Considered {
+                id: orbit[0],
+                weight,
+            },
+        );
+        let _ = weights.set(k, 0);
+        seen_orbits.insert(k);
+
+        let joins = count_joins(graph, cells, orbit)?;
+        if joins < orbit.len() || (exclusive[k] && exclusive_removed) {
+            on_event(edges, CarveEvent::RejectedCycle { id: orbit[0] });
+            rejected.push(k);
+            continue;
+        }
+        exclusive_removed |= exclusive[k];
+        remove_orbit(graph, edges, cells, orbit, on_event)?;
+
+        // Update weight of each neighboring orbit that has not been
+        // processed yet
+        for &id in orbit {
+            for neighbor in graph.neighbor_edges(id) {
+                if let Some(&j) = orbit_of.get(&neighbor) {
+                    if !seen_orbits.contains(&j) {
+                        let _ = weights.set(j, graph.weight(edges, orbits[j][0])?);
+                    }
+                }
+            }
+        }
+    }
+
+    // Join what is left, preferring the orbits creating the fewest loops
+    let mut loops = 0;
+    loop {
+        let mut best = None;
+        for &k in &rejected {
+            let joins = count_joins(graph, cells, &orbits[k])?;
+            let extra = orbits[k].len() - joins;
+            if joins > 0 && best.is_none_or(|(_, e)| extra < e) {
+                best = Some((k, extra));
+            }
+        }
+        let Some((k, extra)) = best else {
+            break;
+        };
+        remove_orbit(graph, edges, cells, &orbits[k], on_event)?;
+        loops += extra;
+    }
+
+    Some(loops)
+}
+
+// Number of walls of an orbit that would join two pieces not joined yet if
+// the walls were removed one after another
+fn count_joins<G: WallGraph>(
+    graph: &G,
+    cells: &UnionFind<usize>,
+    orbit: &[usize],
+) -> Option<usize> {
+    let mut roots = Vec::new();
+    let mut index = |cell: usize| {
+        let root = cells.find(cell);
+        roots.iter().position(|&r| r == root).unwrap_or_else(|| {
+            roots.push(root);
+            roots.len() - 1
+        })
+    };
+    let mut pairs = Vec::with_capacity(orbit.len());
+    for &id in orbit {
+        let (cell_a, cell_b) = graph.adjacent_cells(id)?;
+        pairs.push((index(cell_a), index(cell_b)));
+    }
+    let mut pieces = UnionFind::<usize>::new(roots.len());
+    Some(
+        pairs
+            .into_iter()
+            .filter(|&(a, b)| pieces.union(a, b))
+            .count(),
+    )
+}
+
+fn remove_orbit<G: WallGraph>(
+    graph: &G,
+    edges: &mut [bool],
+    cells: &mut UnionFind<usize>,
+    orbit: &[usize],
+    on_event: &mut dyn FnMut(&[bool], CarveEvent),
+) -> Option<()> {
+    for &id in orbit {
+        let (cell_a, cell_b) = graph.adjacent_cells(id)?;
+        cells.union(cell_a, cell_b);
+        edges[id] = false;
+        on_event(edges, CarveEvent::Removed { id });
+    }
+    Some(())
+}
@@ -1,2 +1,20 @@
+pub mod animate;
+pub mod batch;
+pub mod classic;
+pub mod compact;
+pub mod difficulty;
+pub mod edit;
+pub mod explore;
 pub mod fenwick_tree;
+pub mod hex;
+pub mod image;
+mod kruskal;
+pub mod mask;
 pub mod maze;
+pub mod maze3d;
+pub mod parse;
+pub mod polar;
+pub mod render;
+pub mod solve;
+pub mod tiled;
+mod wilson;
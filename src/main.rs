@@ -1,6 +1,44 @@
-use maze::maze::{Maze, WallWeights};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+use maze::batch::{self, Format, Sweep};
+use maze::maze::{Algorithm, Maze, Topology, WallWeights};
+
+const USAGE: &str = "\
+usage: maze
+       maze batch [options]
+
+Generates every combination of size, preset and seed, in parallel.
+
+options:
+  --sizes 10x10,20x20      widths and heights (default 20x20)
+  --seeds 0..1000          a range or a list of seeds (default 0..100)
+  --preset uniform,ramp    built-in weights, repeatable (default uniform)
+  --weights NAME=W1,..,W24 custom weights in the order of WallType::ALL,
+                           repeatable
+  --algorithm weighted     weighted or uniform (default weighted)
+  --topology plane         plane, cylinder, torus, mobius or klein
+  --weave 0.0              weave density
+  --threads 0              threads, 0 for one per core
+  --format jsonl           jsonl or binary
+  --output PATH            file to write to (default stdout)";
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("batch") {
+        if let Err(message) = run_batch(&args[1..]) {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        return;
+    }
+    if !args.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
     let config = WallWeights {
         type_111x111: 1,
         type_111x011: 2,
@@ -30,3 +68,120 @@ fn main() {
     let maze = Maze::build(20, 20, config).unwrap();
     maze.print();
 }
+
+fn run_batch(args: &[String]) -> Result<(), String> {
+    let mut sweep = Sweep::default();
+    let mut presets = Vec::new();
+    let mut format = Format::JsonLines;
+    let mut output = None;
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else {
+            return Err(format!("missing value for {}", pair[0]));
+        };
+        match flag.as_str() {
+            "--sizes" => {
+                sweep.sizes = value
+                    .split(',')
+                    .map(parse_size)
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("invalid sizes {}", value))?
+            }
+            "--seeds" => {
+                sweep.seeds =
+                    parse_seeds(value).ok_or_else(|| format!("invalid seeds {}", value))?
+            }
+            "--preset" => {
+                for name in value.split(',') {
+                    let weights = batch::get_preset(name).ok_or_else(|| {
+                        format!(
+                            "unknown preset {}, expected one of {:?}",
+                            name,
+                            batch::PRESETS
+                        )
+                    })?;
+                    presets.push((name.to_string(), weights));
+                }
+            }
+            "--weights" => presets
+                .push(parse_weights(value).ok_or_else(|| format!("invalid weights {}", value))?),
+            "--algorithm" => {
+                sweep.options.algorithm = match value.as_str() {
+                    "weighted" => Algorithm::Weighted,
+                    "uniform" => Algorithm::UniformSpanningTree,
+                    _ => return Err(format!("unknown algorithm {}", value)),
+                }
+            }
+            "--topology" => {
+                sweep.options.topology = match value.as_str() {
+                    "plane" => Topology::PLANE,
+                    "cylinder" => Topology::CYLINDER,
+                    "torus" => Topology::TORUS,
+                    "mobius" => Topology::MOBIUS,
+                    "klein" => Topology::KLEIN,
+                    _ => return Err(format!("unknown topology {}", value)),
+                }
+            }
+            "--weave" => {
+                sweep.options.weave_density = value
+                    .parse()
+                    .map_err(|_| format!("invalid weave density {}", value))?
+            }
+            "--threads" => {
+                sweep.threads = value
+                    .parse()
+                    .map_err(|_| format!("invalid thread count {}", value))?
+            }
+            "--format" => {
+                format = match value.as_str() {
+                    "jsonl" => Format::JsonLines,
+                    "binary" => Format::Binary,
+                    _ => return Err(format!("unknown format {}", value)),
+                }
+            }
+            "--output" => output = Some(value.clone()),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    if !presets.is_empty() {
+        sweep.presets = presets;
+    }
+
+    let out: Box<dyn Write> = match output {
+        Some(path) => {
+            Box::new(File::create(&path).map_err(|e| format!("cannot create {}: {}", path, e))?)
+        }
+        None => Box::new(io::stdout().lock()),
+    };
+    batch::export(&sweep, format, BufWriter::new(out))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// A size written as `WIDTHxHEIGHT`
+fn parse_size(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = text.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+// Seeds written as a range `START..END` or a comma separated list
+fn parse_seeds(text: &str) -> Option<Vec<u64>> {
+    match text.split_once("..") {
+        Some((start, end)) => Some((start.parse().ok()?..end.parse().ok()?).collect()),
+        None => text.split(',').map(|seed| seed.parse().ok()).collect(),
+    }
+}
+
+// Named weights written as `NAME=W1,..,W24`
+fn parse_weights(text: &str) -> Option<(String, WallWeights)> {
+    let (name, list) = text.split_once('=')?;
+    let values: Vec<u32> = list
+        .split(',')
+        .map(|weight| weight.parse().ok())
+        .collect::<Option<_>>()?;
+    if values.len() != 24 {
+        return None;
+    }
+    let mut values = values.into_iter();
+    let weights = WallWeights::from_fn(|_| values.next().unwrap_or(0));
+    Some((name.to_string(), weights))
+}
@@ -0,0 +1,178 @@
+// A cell mask selects which cells of a `width` x `height` grid take part in a
+// maze. Excluded cells are rendered as solid blocks and are never connected to
+// the rest of the maze, which makes it possible to reserve areas (goal rooms,
+// obstacles) or to carve mazes into arbitrary shapes such as letters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMask {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl CellMask {
+    // Constructs a mask with every cell included
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![true; width * height],
+        }
+    }
+
+    // Constructs a mask from rows of booleans, `true` meaning included
+    pub fn from_bitmap(bitmap: &[Vec<bool>]) -> Option<Self> {
+        let height = bitmap.len();
+        let width = bitmap.first()?.len();
+        if bitmap.iter().any(|row| row.len() != width) {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            cells: bitmap.concat(),
+        })
+    }
+
+    // Constructs a mask from ASCII art, one character per cell. `.` marks an
+    // included cell and `#` an excluded one. Blank lines and surrounding
+    // whitespace are ignored, e.g.
+    //
+    // ....##....
+    // ....##....
+    // ..........
+    pub fn from_ascii(art: &str) -> Option<Self> {
+        let mut bitmap = Vec::new();
+        for line in art.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let row = line
+                .chars()
+                .map(|c| match c {
+                    '.' => Some(true),
+                    '#' => Some(false),
+                    _ => None,
+                })
+                .collect::<Option<Vec<bool>>>()?;
+            bitmap.push(row);
+        }
+        Self::from_bitmap(&bitmap)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Whether the cell at (`row`, `col`) is part of the maze
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row < self.height && col < self.width && self.cells[row * self.width + col]
+    }
+
+    // Include or exclude the cell at (`row`, `col`)
+    pub fn set(&mut self, row: usize, col: usize, included: bool) {
+        if row < self.height && col < self.width {
+            self.cells[row * self.width + col] = included;
+        }
+    }
+
+    // Exclude every cell of the `height` x `width` rectangle whose top-left
+    // corner is at (`row`, `col`)
+    pub fn exclude_rect(&mut self, row: usize, col: usize, height: usize, width: usize) {
+        for r in row..(row + height).min(self.height) {
+            for c in col..(col + width).min(self.width) {
+                self.set(r, c, false);
+            }
+        }
+    }
+
+    // Number of included cells
+    pub fn count(&self) -> usize {
+        self.cells.iter().filter(|&&included| included).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildError, BuildOptions, Maze, Topology, WallWeights};
+    use crate::solve;
+
+    fn build(mask: &CellMask, topology: Topology, seed: u64) -> Result<Maze, BuildError> {
+        let options = BuildOptions {
+            mask: Some(mask.clone()),
+            topology,
+            seed: Some(seed),
+            ..Default::default()
+        };
+        Maze::build_with_options(
+            mask.width(),
+            mask.height(),
+            WallWeights::from_fn(|_| 1),
+            options,
+        )
+    }
+
+    #[test]
+    fn excluded_cells_are_left_out_of_the_maze() {
+        let mask = CellMask::from_ascii(
+            "
+            #.......
+            ..##....
+            ..##..#.
+            ......##
+            ",
+        )
+        .unwrap();
+        assert_eq!((mask.width(), mask.height(), mask.count()), (8, 4, 24));
+        for topology in [Topology::PLANE, Topology::TORUS] {
+            for seed in 0..5 {
+                let maze = build(&mask, topology, seed).unwrap();
+                assert!(maze.is_connected());
+                let mut openings = 0;
+                for row in 0..4 {
+                    for col in 0..8 {
+                        let neighbors = maze.neighbors(row, col);
+                        if !mask.contains(row, col) {
+                            assert!(maze.grid[row * 2 + 1][col * 2 + 1]);
+                            assert!(neighbors.is_empty());
+                            assert!(solve::bfs(&maze, (0, 1), (row, col)).is_none());
+                            continue;
+                        }
+                        assert!(neighbors.iter().all(|&(r, c)| mask.contains(r, c)));
+                        assert!(solve::bfs(&maze, (0, 1), (row, col)).is_some());
+                        openings += neighbors.len();
+                    }
+                }
+                assert_eq!(openings / 2, mask.count() - 1, "{:?}", topology);
+            }
+        }
+    }
+
+    #[test]
+    fn masks_must_fit_the_maze_and_hold_connected_cells() {
+        let mut mask = CellMask::new(5, 4);
+        mask.exclude_rect(0, 2, 4, 1);
+        assert_eq!(mask.count(), 16);
+        assert_eq!(
+            build(&mask, Topology::PLANE, 0).err(),
+            Some(BuildError::Disconnected)
+        );
+        // Joining the left and right borders reconnects both halves
+        assert!(build(&mask, Topology::CYLINDER, 0).is_ok());
+
+        mask.exclude_rect(0, 0, 4, 5);
+        assert_eq!(
+            build(&mask, Topology::PLANE, 0).err(),
+            Some(BuildError::EmptyMask)
+        );
+
+        let options = BuildOptions {
+            mask: Some(CellMask::new(4, 4)),
+            ..Default::default()
+        };
+        let res = Maze::build_with_options(5, 4, WallWeights::from_fn(|_| 1), options);
+        assert_eq!(res.err(), Some(BuildError::MaskSizeMismatch));
+    }
+}
@@ -1,238 +1,1166 @@
 use std::collections::HashSet;
+use std::fmt;
 
 use disjoint_sets::UnionFind;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
 
-use crate::fenwick_tree::FenwickTree;
+use crate::kruskal::{self, CarveEvent, WallGraph};
+use crate::mask::CellMask;
+use crate::wilson;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Maze {
     pub width: usize,
     pub height: usize,
     pub grid: Vec<Vec<bool>>,
+    pub topology: Topology,
+    // Cells where one passage runs over another
+    pub crossings: Vec<Crossing>,
+}
+
+// Direction of a passage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    // Running west to east
+    Horizontal,
+    // Running north to south
+    Vertical,
+}
+
+// Direction of a move between two cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+        }
+    }
+
+    pub fn axis(self) -> Axis {
+        match self {
+            Direction::North | Direction::South => Axis::Vertical,
+            Direction::West | Direction::East => Axis::Horizontal,
+        }
+    }
+
+    // Change in (row, col) when moving one cell in this direction
+    fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+            Direction::East => (0, 1),
+        }
+    }
+}
+
+// A cell where a passage along `over` runs on top, while the passage along
+// the other axis tunnels underneath and does not connect to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crossing {
+    pub row: usize,
+    pub col: usize,
+    pub over: Axis,
+}
+
+// A move from one cell to a neighboring cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub to: (usize, usize),
+    // Direction the move leaves the starting cell in
+    pub direction: Direction,
+    // Number of cells travelled, 2 when passing under a crossing
+    pub length: u32,
+    // Whether the move crosses a twisted border, which swaps left and right
+    pub mirrored: bool,
+}
+
+// How a pair of opposite borders is joined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Boundary {
+    // The borders are solid walls
+    #[default]
+    Closed,
+    // Leaving through one border enters through the opposite one
+    Wrapped,
+    // Like `Wrapped`, but the opposite border is entered upside down
+    Twisted,
+}
+
+// The surface a maze is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Topology {
+    // How the left and right borders are joined
+    pub horizontal: Boundary,
+    // How the top and bottom borders are joined
+    pub vertical: Boundary,
+}
+
+impl Topology {
+    pub const PLANE: Topology = Topology {
+        horizontal: Boundary::Closed,
+        vertical: Boundary::Closed,
+    };
+    pub const CYLINDER: Topology = Topology {
+        horizontal: Boundary::Wrapped,
+        vertical: Boundary::Closed,
+    };
+    pub const TORUS: Topology = Topology {
+        horizontal: Boundary::Wrapped,
+        vertical: Boundary::Wrapped,
+    };
+    pub const MOBIUS: Topology = Topology {
+        horizontal: Boundary::Twisted,
+        vertical: Boundary::Closed,
+    };
+    pub const KLEIN: Topology = Topology {
+        horizontal: Boundary::Twisted,
+        vertical: Boundary::Wrapped,
+    };
+}
+
+// A wall between two orthogonally adjacent cells, addressed by the cell on
+// its west or north side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Edge {
+    // Wall between (row, col) and (row, col + 1)
+    East(usize, usize),
+    // Wall between (row, col) and (row + 1, col)
+    South(usize, usize),
+}
+
+// Optional generation settings for `Maze::build_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    // Cells to include in the maze. Excluded cells become solid blocks.
+    pub mask: Option<CellMask>,
+    // Walls that must remain in the generated maze
+    pub fixed_walls: Vec<Edge>,
+    // Walls that must be removed from the generated maze
+    pub forced_openings: Vec<Edge>,
+    // How opposite borders are joined
+    pub topology: Topology,
+    // Chance, between 0 and 1, of placing a crossing at each cell away from
+    // the borders. Values outside that range are clamped to it, while NaN
+    // and infinite values are rejected.
+    pub weave_density: f64,
+    pub algorithm: Algorithm,
+    // Symmetry the maze must keep. Fixed walls and forced openings apply to
+    // all their symmetric images.
+    pub symmetry: Symmetry,
+    // Seed of the random generator. The same seed with the same size, weights
+    // and options always gives the same maze with this version of the crate,
+    // while a fresh maze is generated every time without one.
+    pub seed: Option<u64>,
+}
+
+// How the walls to remove are chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Algorithm {
+    // Weighted random order driven by `WallWeights`
+    #[default]
+    Weighted,
+    // Wilson's algorithm, which ignores `WallWeights` and picks every maze
+    // allowed by the other options with the same probability
+    UniformSpanningTree,
+}
+
+// Symmetry of a generated maze. Walls are removed together with all their
+// images, and the maze stays a tree whenever a symmetric tree exists. Turning
+// a maze with an even number of rows and columns around its center always
+// leaves a single loop around the center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Symmetry {
+    #[default]
+    None,
+    // The maze looks the same upside down
+    HalfTurn,
+    // The maze looks the same after a quarter turn, which needs a square
+    // maze
+    QuarterTurn,
+    // The left half mirrors the right half
+    MirrorLeftRight,
+    // The top half mirrors the bottom half
+    MirrorTopBottom,
+}
+
+impl Symmetry {
+    // The cell together with its images
+    fn get_cell_images(
+        self,
+        width: usize,
+        height: usize,
+        (row, col): (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let half_turn = (height - 1 - row, width - 1 - col);
+        match self {
+            Symmetry::None => vec![(row, col)],
+            Symmetry::HalfTurn => vec![(row, col), half_turn],
+            Symmetry::QuarterTurn => vec![
+                (row, col),
+                (col, width - 1 - row),
+                half_turn,
+                (height - 1 - col, row),
+            ],
+            Symmetry::MirrorLeftRight => vec![(row, col), (row, width - 1 - col)],
+            Symmetry::MirrorTopBottom => vec![(row, col), (height - 1 - row, col)],
+        }
+    }
+
+    // The wall together with its images, without repeats
+    fn get_edge_images(self, width: usize, height: usize, edge: Edge) -> Vec<Edge> {
+        let (near, far) = match edge {
+            Edge::East(row, col) => ((row, col), (row, col + 1)),
+            Edge::South(row, col) => ((row, col), (row + 1, col)),
+        };
+        let near = self.get_cell_images(width, height, near);
+        let far = self.get_cell_images(width, height, far);
+        let mut res = Vec::with_capacity(near.len());
+        for (a, b) in near.into_iter().zip(far) {
+            let (a, b) = (a.min(b), a.max(b));
+            let image = if a.0 == b.0 {
+                Edge::East(a.0, a.1)
+            } else {
+                Edge::South(a.0, a.1)
+            };
+            if !res.contains(&image) {
+                res.push(image);
+            }
+        }
+        res
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    // The maze must be at least 2x2
+    InvalidSize,
+    // The mask dimensions differ from the maze dimensions
+    MaskSizeMismatch,
+    // The mask does not include any cell
+    EmptyMask,
+    // The included cells cannot all be connected to each other
+    Disconnected,
+    // A constrained edge lies outside the maze, touches an excluded cell, or
+    // is both fixed and forced open
+    InvalidEdge,
+    // The forced openings form a loop, so the maze cannot be a tree
+    ConstraintLoop,
+    // The symmetry needs closed borders, no crossings, weighted generation
+    // and a symmetric mask, as well as a square maze for quarter turns
+    UnsupportedSymmetry,
+    // The weave density is NaN or infinite
+    InvalidWeaveDensity,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidSize => write!(f, "maze must be at least 2x2"),
+            BuildError::MaskSizeMismatch => write!(f, "mask size does not match maze size"),
+            BuildError::EmptyMask => write!(f, "mask does not include any cell"),
+            BuildError::Disconnected => write!(f, "cells cannot all be connected"),
+            BuildError::InvalidEdge => write!(f, "constrained edge is not a valid edge"),
+            BuildError::ConstraintLoop => write!(f, "forced openings form a loop"),
+            BuildError::UnsupportedSymmetry => {
+                write!(f, "symmetry is not supported with these options")
+            }
+            BuildError::InvalidWeaveDensity => write!(f, "weave density must be a finite number"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+// Walls of a generated maze before the raster is drawn
+pub(crate) struct CarvedMaze {
+    // Whether each edge is a wall, by edge ID
+    pub(crate) edges: Vec<bool>,
+    pub(crate) mask: CellMask,
+    pub(crate) topology: Topology,
+    pub(crate) crossings: Vec<Crossing>,
+}
+
+// A step of maze generation, reported by `Maze::build_with_events` in the
+// order it happens. Forced openings and the walls removed around crossings
+// are reported as removed before any other wall is considered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationEvent {
+    // A wall was drawn at random, given its type and weight at that moment
+    EdgeConsidered {
+        edge: Edge,
+        wall_type: WallType,
+        weight: u32,
+    },
+    EdgeRemoved {
+        edge: Edge,
+    },
+    // A wall was kept because removing it would create a loop
+    EdgeRejectedCycle {
+        edge: Edge,
+    },
+    // Reported last for symmetric mazes, with the number of loops left to
+    // join cells no symmetric tree could join. It is 0 for a tree.
+    LoopsCreated {
+        count: usize,
+    },
 }
 
 impl Maze {
     pub fn build(width: usize, height: usize, config: WallWeights) -> Option<Maze> {
+        Self::build_with_options(width, height, config, BuildOptions::default()).ok()
+    }
+
+    pub fn build_with_options(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: BuildOptions,
+    ) -> Result<Maze, BuildError> {
+        Self::build_with_events(width, height, config, options, |_| {})
+    }
+
+    // Same as `build_with_options`, calling `on_event` at every step of the
+    // generation
+    pub fn build_with_events<F>(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: BuildOptions,
+        on_event: F,
+    ) -> Result<Maze, BuildError>
+    where
+        F: FnMut(GenerationEvent),
+    {
+        let carved = Self::carve(width, height, config, options, on_event)?;
+        let grid = build_grid(width, height, carved.topology, &carved.edges, &carved.mask)
+            .ok_or(BuildError::InvalidSize)?;
+        Ok(Maze {
+            width,
+            height,
+            grid,
+            topology: carved.topology,
+            crossings: carved.crossings,
+        })
+    }
+
+    // Generate the walls of a maze as with `build_with_events`, without
+    // drawing the raster
+    pub(crate) fn carve<F>(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: BuildOptions,
+        mut on_event: F,
+    ) -> Result<CarvedMaze, BuildError>
+    where
+        F: FnMut(GenerationEvent),
+    {
         if width < 2 || height < 2 {
-            return None;
+            return Err(BuildError::InvalidSize);
+        }
+        if !options.weave_density.is_finite() {
+            return Err(BuildError::InvalidWeaveDensity);
+        }
+
+        let mask = options.mask.unwrap_or_else(|| CellMask::new(width, height));
+        if mask.width() != width || mask.height() != height {
+            return Err(BuildError::MaskSizeMismatch);
+        }
+        if mask.count() == 0 {
+            return Err(BuildError::EmptyMask);
         }
 
-        let number_of_edges = ((width - 1) * height) + ((height - 1) * width);
+        let topology = options.topology;
+        let symmetry = options.symmetry;
+        if symmetry != Symmetry::None {
+            if (symmetry == Symmetry::QuarterTurn && width != height)
+                || topology != Topology::PLANE
+                || options.weave_density > 0.0
+                || options.algorithm != Algorithm::Weighted
+            {
+                return Err(BuildError::UnsupportedSymmetry);
+            }
+            let symmetric_mask = (0..height).all(|row| {
+                (0..width).all(|col| {
+                    symmetry
+                        .get_cell_images(width, height, (row, col))
+                        .into_iter()
+                        .all(|(r, c)| mask.contains(r, c) == mask.contains(row, col))
+                })
+            });
+            if !symmetric_mask {
+                return Err(BuildError::UnsupportedSymmetry);
+            }
+        }
+        let number_of_edges = get_edge_count(width, height, topology);
         let number_of_cells = width * height;
 
         let mut cells = UnionFind::<usize>::new(number_of_cells);
         let mut edges: Vec<bool> = vec![true; number_of_edges];
-        let mut weights = FenwickTree::<u32>::with_len(number_of_edges);
 
-        let mut seen_edges = HashSet::new();
-
-        // Initialize weight of every edge
-        for i in 0..number_of_edges {
-            let _ = weights.set(i, get_weight(width, height, &edges, &config, i)?);
+        // Edges touching an excluded cell are never removed
+        let is_active = |id: usize| match get_adjacent_cells(width, height, topology, id) {
+            Some((cell_a, cell_b)) => {
+                mask.contains(cell_a / width, cell_a % width)
+                    && mask.contains(cell_b / width, cell_b % width)
+            }
+            None => false,
+        };
+        let constrained_ids = |constrained: &[Edge]| {
+            constrained
+                .iter()
+                .flat_map(|&edge| symmetry.get_edge_images(width, height, edge))
+                .map(|edge| {
+                    get_constrained_edge_id(width, height, topology, edge)
+                        .filter(|&id| is_active(id))
+                        .ok_or(BuildError::InvalidEdge)
+                })
+                .collect::<Result<HashSet<usize>, BuildError>>()
+        };
+        let fixed_walls = constrained_ids(&options.fixed_walls)?;
+        let forced_openings = constrained_ids(&options.forced_openings)?;
+        if !fixed_walls.is_disjoint(&forced_openings) {
+            return Err(BuildError::InvalidEdge);
         }
 
-        // Start generating maze
-        for _ in 0..number_of_edges {
-            // Select and set weight of random edge to 0
-            let rand_num = rand::thread_rng().gen_range(1..=weights.get_final_sum());
-            let edge_id_to_remove = weights.get_lower(rand_num).ok()?;
-            let _ = weights.set(edge_id_to_remove, 0);
-            seen_edges.insert(edge_id_to_remove);
-
-            // Get coordinates of current edge
-            let (row, col) = get_edge_coord(width, height, edge_id_to_remove)?;
-
-            // Determine if edge should be removed by looking at adjacent cells
-            let cell_a;
-            let cell_b;
-            if row % 2 == 0 {
-                cell_a = get_cell_id(width, height, row, col - 1)?;
-                cell_b = get_cell_id(width, height, row, col + 1)?;
-            } else {
-                cell_a = get_cell_id(width, height, row - 1, col)?;
-                cell_b = get_cell_id(width, height, row + 1, col)?;
+        // Make sure the fixed walls do not cut the maze into pieces
+        let mut reachable = UnionFind::<usize>::new(number_of_cells);
+        for i in (0..number_of_edges).filter(|&i| is_active(i) && !fixed_walls.contains(&i)) {
+            let (cell_a, cell_b) =
+                get_adjacent_cells(width, height, topology, i).ok_or(BuildError::InvalidSize)?;
+            reachable.union(cell_a, cell_b);
+        }
+        let mut reachable_roots = HashSet::new();
+        for i in 0..number_of_cells {
+            if mask.contains(i / width, i % width) {
+                reachable_roots.insert(reachable.find(i));
             }
+        }
+        if reachable_roots.len() != 1 {
+            return Err(BuildError::Disconnected);
+        }
+
+        // Open forced edges before any weight is computed so that the wall
+        // types of their neighbors reflect them
+        for &id in &forced_openings {
+            let (cell_a, cell_b) =
+                get_adjacent_cells(width, height, topology, id).ok_or(BuildError::InvalidSize)?;
             if !cells.union(cell_a, cell_b) {
-                continue;
+                return Err(BuildError::ConstraintLoop);
             }
-            edges[edge_id_to_remove] = false;
-
-            // Find neighboring edges
-            let neighbors;
-            if row % 2 == 0 {
-                if row == 0 {
-                    let n1 = get_edge_id(width, height, row + 1, col - 1)?;
-                    let n2 = get_edge_id(width, height, row + 2, col)?;
-                    let n3 = get_edge_id(width, height, row + 1, col + 1)?;
-                    neighbors = vec![n1, n2, n3];
-                } else if row == (2 * height - 2) {
-                    let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-                    let n2 = get_edge_id(width, height, row - 2, col)?;
-                    let n3 = get_edge_id(width, height, row - 1, col + 1)?;
-                    neighbors = vec![n1, n2, n3];
-                } else {
-                    let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-                    let n2 = get_edge_id(width, height, row - 2, col)?;
-                    let n3 = get_edge_id(width, height, row - 1, col + 1)?;
-                    let n4 = get_edge_id(width, height, row + 1, col - 1)?;
-                    let n5 = get_edge_id(width, height, row + 2, col)?;
-                    let n6 = get_edge_id(width, height, row + 1, col + 1)?;
-                    neighbors = vec![n1, n2, n3, n4, n5, n6];
+            edges[id] = false;
+        }
+        let edge_of = |id: usize| get_edge(width, height, topology, id);
+        for &id in &forced_openings {
+            if let Some(edge) = edge_of(id) {
+                on_event(GenerationEvent::EdgeRemoved { edge });
+            }
+        }
+
+        let is_free = |id: usize| {
+            is_active(id) && !fixed_walls.contains(&id) && !forced_openings.contains(&id)
+        };
+
+        // Place crossings before any other edge is removed. The passage on top
+        // joins the crossing cell with its two neighbors, while the tunnel
+        // underneath joins the two other neighbors directly. Crossings never
+        // touch each other and only join cells that are not connected yet.
+        let mut crossings: Vec<Crossing> = Vec::new();
+        let mut crossing_edges = HashSet::new();
+        let weave_density = options.weave_density.clamp(0.0, 1.0);
+        let mut rng: Box<dyn RngCore> = match options.seed {
+            Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+            None => Box::new(rand::thread_rng()),
+        };
+        for row in 1..height - 1 {
+            for col in 1..width - 1 {
+                if weave_density == 0.0 || !rng.gen_bool(weave_density) {
+                    continue;
                 }
-            } else {
-                if col == 0 {
-                    let n1 = get_edge_id(width, height, row - 1, col + 1)?;
-                    let n2 = get_edge_id(width, height, row, col + 2)?;
-                    let n3 = get_edge_id(width, height, row + 1, col + 1)?;
-                    neighbors = vec![n1, n2, n3];
-                } else if col == (2 * width - 2) {
-                    let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-                    let n2 = get_edge_id(width, height, row, col - 2)?;
-                    let n3 = get_edge_id(width, height, row + 1, col - 1)?;
-                    neighbors = vec![n1, n2, n3];
+                if crossings
+                    .iter()
+                    .any(|c| c.row.abs_diff(row) + c.col.abs_diff(col) <= 1)
+                {
+                    continue;
+                }
+
+                let surrounding = [
+                    Edge::South(row - 1, col),
+                    Edge::South(row, col),
+                    Edge::East(row, col - 1),
+                    Edge::East(row, col),
+                ]
+                .map(|edge| get_constrained_edge_id(width, height, topology, edge));
+                let [Some(north), Some(south), Some(west), Some(east)] = surrounding else {
+                    continue;
+                };
+                if ![north, south, west, east].into_iter().all(is_free) {
+                    continue;
+                }
+
+                let center = row * width + col;
+                let neighbors = [center - width, center + width, center - 1, center + 1];
+                let mut roots: HashSet<usize> = neighbors.iter().map(|&c| cells.find(c)).collect();
+                roots.insert(cells.find(center));
+                if roots.len() != 5 {
+                    continue;
+                }
+
+                let over = if rng.gen_bool(0.5) {
+                    Axis::Vertical
                 } else {
-                    let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-                    let n2 = get_edge_id(width, height, row, col - 2)?;
-                    let n3 = get_edge_id(width, height, row + 1, col - 1)?;
-                    let n4 = get_edge_id(width, height, row - 1, col + 1)?;
-                    let n5 = get_edge_id(width, height, row + 1, col + 1)?;
-                    let n6 = get_edge_id(width, height, row, col + 2)?;
-                    neighbors = vec![n1, n2, n3, n4, n5, n6];
+                    Axis::Horizontal
+                };
+                let [n, s, w, e] = neighbors;
+                let (over_cells, under_cells) = match over {
+                    Axis::Vertical => ((n, s), (w, e)),
+                    Axis::Horizontal => ((w, e), (n, s)),
+                };
+                cells.union(center, over_cells.0);
+                cells.union(center, over_cells.1);
+                cells.union(under_cells.0, under_cells.1);
+                for id in [north, south, west, east] {
+                    edges[id] = false;
+                    crossing_edges.insert(id);
+                    if let Some(edge) = edge_of(id) {
+                        on_event(GenerationEvent::EdgeRemoved { edge });
+                    }
                 }
+                crossings.push(Crossing { row, col, over });
             }
+        }
 
-            // Update weight of each neighbor if the neighbor has not been processed yet
-            for id in neighbors {
-                if !seen_edges.contains(&id) {
-                    let _ = weights.set(id, get_weight(width, height, &edges, &config, id)?);
+        // Start generating maze. Inactive, constrained and crossing edges are
+        // never selected.
+        let candidates: Vec<bool> = (0..number_of_edges)
+            .map(|i| is_free(i) && !crossing_edges.contains(&i))
+            .collect();
+        let graph = SquareGrid {
+            width,
+            height,
+            topology,
+            config: &config,
+        };
+        let mut on_carve = |edges: &[bool], event: CarveEvent| {
+            let event = match event {
+                CarveEvent::Considered { id, weight } => {
+                    let Some(wall_type) = get_wall_type(width, height, topology, edges, id) else {
+                        return;
+                    };
+                    edge_of(id).map(|edge| GenerationEvent::EdgeConsidered {
+                        edge,
+                        wall_type,
+                        weight,
+                    })
+                }
+                CarveEvent::Removed { id } => {
+                    edge_of(id).map(|edge| GenerationEvent::EdgeRemoved { edge })
                 }
+                CarveEvent::RejectedCycle { id } => {
+                    edge_of(id).map(|edge| GenerationEvent::EdgeRejectedCycle { edge })
+                }
+            };
+            if let Some(event) = event {
+                on_event(event);
+            }
+        };
+        let loops = match options.algorithm {
+            _ if symmetry != Symmetry::None => {
+                let (orbits, exclusive) = get_orbits(width, height, symmetry, &candidates)
+                    .ok_or(BuildError::InvalidSize)?;
+                kruskal::carve_symmetric(
+                    &graph,
+                    &mut edges,
+                    &mut cells,
+                    &orbits,
+                    &exclusive,
+                    &mut rng,
+                    &mut on_carve,
+                )
+                .map(Some)
+                .ok_or(BuildError::InvalidSize)
             }
+            Algorithm::Weighted => kruskal::carve(
+                &graph,
+                &mut edges,
+                &mut cells,
+                &candidates,
+                &mut rng,
+                &mut on_carve,
+            )
+            .map(|_| None)
+            .ok_or(BuildError::InvalidSize),
+            Algorithm::UniformSpanningTree => wilson::carve(
+                &graph,
+                &mut edges,
+                &mut cells,
+                &candidates,
+                &mut rng,
+                &mut on_carve,
+            )
+            .map(|_| None)
+            .ok_or(BuildError::Disconnected),
+        }?;
+
+        // Make sure every included cell has been connected
+        let mut cell_roots = HashSet::new();
+        for i in 0..number_of_cells {
+            if mask.contains(i / width, i % width) {
+                cell_roots.insert(cells.find(i));
+            }
+        }
+        if cell_roots.len() != 1 {
+            return Err(BuildError::Disconnected);
+        }
+        if let Some(count) = loops {
+            on_event(GenerationEvent::LoopsCreated { count });
         }
 
-        let mut grid = vec![vec![false; width * 2 + 1]; height * 2 + 1];
-        for i in 0..width * 2 + 1 {
-            grid[0][i] = true;
-            grid[height * 2][i] = true;
+        Ok(CarvedMaze {
+            edges,
+            mask,
+            topology,
+            crossings,
+        })
+    }
+
+    // Crossings are drawn as `||` or `==` along the direction of the passage
+    // at that spot, so the tunnel shows on either side of the passage on top
+    pub fn to_ascii(&self) -> String {
+        let mut res = String::new();
+        for (i, row) in self.grid.iter().enumerate() {
+            let mut line = String::with_capacity(row.len() * 2);
+            for (j, &wall) in row.iter().enumerate() {
+                if wall {
+                    line.push_str("##");
+                    continue;
+                }
+                let passage = self.crossings.iter().find_map(|c| {
+                    let (row, col) = (c.row * 2 + 1, c.col * 2 + 1);
+                    if (i, j) == (row, col) {
+                        Some(c.over)
+                    } else if i.abs_diff(row) + j.abs_diff(col) == 1 {
+                        // The tunnel runs across the passage on top
+                        let on_top = (i == row) == (c.over == Axis::Horizontal);
+                        match (on_top, c.over) {
+                            (true, _) => None,
+                            (false, Axis::Vertical) => Some(Axis::Horizontal),
+                            (false, Axis::Horizontal) => Some(Axis::Vertical),
+                        }
+                    } else {
+                        None
+                    }
+                });
+                line.push_str(match passage {
+                    Some(Axis::Horizontal) => "==",
+                    Some(Axis::Vertical) => "||",
+                    None => "..",
+                });
+            }
+            res.push_str(&line);
+            res.push('\n');
         }
-        for i in 0..height * 2 + 1 {
-            grid[i][0] = true;
-            grid[i][width * 2] = true;
+        res
+    }
+
+    pub fn print(&self) {
+        print!("{}", self.to_ascii());
+    }
+
+    // Cells reachable in one move from the cell at (`row`, `col`), following
+    // joined borders and tunnels
+    pub fn neighbors(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        self.moves(row, col).into_iter().map(|m| m.to).collect()
+    }
+
+    // Every move out of the cell at (`row`, `col`), with the direction it
+    // leaves in. A move passing under a crossing spans two cells.
+    pub fn moves(&self, row: usize, col: usize) -> Vec<Move> {
+        let mut res = Vec::with_capacity(4);
+        if row >= self.height || col >= self.width {
+            return res;
         }
-        for i in 0..height * 2 - 1 {
-            for j in 0..width * 2 - 1 {
-                if (i % 2 == 0) ^ (j % 2 == 0) {
-                    let id = get_edge_id(width, height, i, j)?;
-                    if edges[id] {
-                        grid[i + 1][j + 1] = true;
+        let here = self.crossing_at(row, col);
+        for direction in Direction::ALL {
+            let axis = direction.axis();
+            // Only the passage on top can be followed from a crossing
+            if here.is_some_and(|c| c.over != axis) {
+                continue;
+            }
+            let Some((mut next, mut mirrored)) = self.step(row, col, direction) else {
+                continue;
+            };
+            let mut length = 1;
+            // Passing under a crossing leads to the cell on the other side
+            if self
+                .crossing_at(next.0, next.1)
+                .is_some_and(|c| c.over != axis)
+            {
+                match self.step(next.0, next.1, direction) {
+                    Some((beyond, twisted)) => {
+                        next = beyond;
+                        mirrored ^= twisted;
                     }
-                } else if (i % 2 == 1) && (j % 2 == 1) {
-                    grid[i + 1][j + 1] = true;
+                    None => continue,
                 }
+                length = 2;
             }
+            res.push(Move {
+                to: next,
+                direction,
+                length,
+                mirrored,
+            });
         }
+        res
+    }
 
-        // Make sure every cell has been connected
-        let mut cell_roots = HashSet::new();
-        for i in 0..number_of_cells {
-            cell_roots.insert(cells.find(i));
+    fn crossing_at(&self, row: usize, col: usize) -> Option<&Crossing> {
+        self.crossings.iter().find(|c| c.row == row && c.col == col)
+    }
+
+    // The cell one step away through an open wall, if any, and whether the
+    // step crosses a twisted border
+    fn step(&self, row: usize, col: usize, direction: Direction) -> Option<((usize, usize), bool)> {
+        let (d_row, d_col) = direction.offset();
+        let wall_row = (row * 2 + 1).checked_add_signed(d_row)?;
+        let wall_col = (col * 2 + 1).checked_add_signed(d_col)?;
+        if *self.grid.get(wall_row)?.get(wall_col)? {
+            return None;
         }
-        if cell_roots.len() != 1 {
+        get_adjacent_cell(self.width, self.height, self.topology, row, col, direction)
+    }
+
+    // The cell next to the cell at (`row`, `col`) in `direction`, whether or
+    // not a wall separates them, and whether getting there crosses a twisted
+    // border
+    pub fn adjacent(
+        &self,
+        row: usize,
+        col: usize,
+        direction: Direction,
+    ) -> Option<((usize, usize), bool)> {
+        if row >= self.height || col >= self.width {
             return None;
         }
+        get_adjacent_cell(self.width, self.height, self.topology, row, col, direction)
+    }
 
-        Some(Maze {
-            width,
-            height,
-            grid,
+    // Type of a wall of the maze as seen by the generator, classified by the
+    // walls it currently meets at both ends. `None` if the wall lies outside
+    // the maze or on a closed border.
+    pub fn classify_edge(&self, edge: Edge) -> Option<WallType> {
+        let (width, height, topology) = (self.width, self.height, self.topology);
+        let id = get_constrained_edge_id(width, height, topology, edge)?;
+        get_wall_type_with(width, height, topology, id, |id| {
+            let (row, col) = get_edge_coord(width, height, topology, id)?;
+            Some(self.grid[row + 1][col + 1])
         })
     }
+}
 
-    pub fn print(&self) {
-        for i in 0..self.grid.len() {
-            let mut line = String::with_capacity(self.grid[0].len() * 2);
-            for j in 0..self.grid[0].len() {
-                if self.grid[i][j] {
-                    line.push('#');
-                    line.push('#');
-                } else {
-                    line.push('.');
-                    line.push('.');
+// Raster of the maze where `true` marks a wall, post or excluded cell
+pub(crate) fn build_grid(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    edges: &[bool],
+    mask: &CellMask,
+) -> Option<Vec<Vec<bool>>> {
+    let mut grid = vec![vec![false; width * 2 + 1]; height * 2 + 1];
+    grid[0].fill(true);
+    grid[height * 2].fill(true);
+    for row in grid.iter_mut() {
+        row[0] = true;
+        row[width * 2] = true;
+    }
+    for i in 0..height * 2 - 1 {
+        for j in 0..width * 2 - 1 {
+            if (i % 2 == 0) ^ (j % 2 == 0) {
+                let id = get_edge_id(width, height, topology, i, j)?;
+                if edges[id] {
+                    grid[i + 1][j + 1] = true;
                 }
+            } else if ((i % 2 == 1) && (j % 2 == 1)) || !mask.contains(i / 2, j / 2) {
+                grid[i + 1][j + 1] = true;
             }
-            println!("{}", line);
         }
     }
+
+    // Open the border on both sides of every removed edge crossing a seam
+    let (rows, cols) = get_lattice_size(width, height, topology);
+    let seam_edges = (0..height)
+        .filter(|_| cols == width * 2)
+        .map(|k| (k * 2, width * 2 - 1))
+        .chain(
+            (0..width)
+                .filter(|_| rows == height * 2)
+                .map(|k| (height * 2 - 1, k * 2)),
+        );
+    for (row, col) in seam_edges {
+        let id = get_edge_id(width, height, topology, row, col)?;
+        if edges[id] {
+            continue;
+        }
+        // The far side is the cell across the seam
+        let (far_row, far_col) = if row % 2 == 0 {
+            (row as isize, col as isize + 1)
+        } else {
+            (row as isize + 1, col as isize)
+        };
+        let (far_row, far_col) = normalize_coord(width, height, topology, far_row, far_col)?;
+        grid[row + 1][col + 1] = false;
+        if row % 2 == 0 {
+            grid[far_row + 1][0] = false;
+        } else {
+            grid[0][far_col + 1] = false;
+        }
+    }
+
+    Some(grid)
 }
 
 // ========== Edge and Cell Coordinates-ID Conversion ==========
+//
+// Cells and edges are addressed by (row, col) coordinates on a lattice where
+// cells sit at (even, even), edges at (even, odd) or (odd, even) and posts at
+// (odd, odd). When a border is wrapped, the lattice gains an extra row or
+// column holding the edges (and posts) that cross the seam.
+
+// Number of lattice rows and columns
+fn get_lattice_size(width: usize, height: usize, topology: Topology) -> (usize, usize) {
+    let rows = height * 2 - 1 + usize::from(topology.vertical != Boundary::Closed);
+    let cols = width * 2 - 1 + usize::from(topology.horizontal != Boundary::Closed);
+    (rows, cols)
+}
 
-fn get_edge_coord(width: usize, height: usize, mut id: usize) -> Option<(usize, usize)> {
-    let mut row = 2 * (id / (width * 2 - 1));
-    id %= width * 2 - 1;
-
-    let col;
-    if id >= (width - 1) {
-        row += 1;
-        id -= width - 1;
-        col = id * 2;
+// Number of edges separating horizontally adjacent cells in each row
+fn get_row_edge_count(width: usize, topology: Topology) -> usize {
+    if topology.horizontal == Boundary::Closed {
+        width - 1
     } else {
-        col = id * 2 + 1;
+        width
     }
+}
 
-    if (row >= height * 2 - 1) || (col >= width * 2 - 1) {
-        println!("Given row {} or col {} too large", row, col);
-        return None;
+pub(crate) fn get_edge_count(width: usize, height: usize, topology: Topology) -> usize {
+    let column_edge_rows = if topology.vertical == Boundary::Closed {
+        height - 1
+    } else {
+        height
+    };
+    get_row_edge_count(width, topology) * height + column_edge_rows * width
+}
+
+// Map coordinates that may lie past a wrapped border back onto the lattice.
+// Crossing a twisted border mirrors the other coordinate.
+fn normalize_coord(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    mut row: isize,
+    mut col: isize,
+) -> Option<(usize, usize)> {
+    let (rows, cols) = get_lattice_size(width, height, topology);
+    for _ in 0..4 {
+        if col < 0 || col >= cols as isize {
+            match topology.horizontal {
+                Boundary::Closed => return None,
+                Boundary::Wrapped => col = col.rem_euclid(width as isize * 2),
+                Boundary::Twisted => {
+                    col = col.rem_euclid(width as isize * 2);
+                    row = (height as isize * 2 - 2) - row;
+                }
+            }
+        } else if row < 0 || row >= rows as isize {
+            match topology.vertical {
+                Boundary::Closed => return None,
+                Boundary::Wrapped => row = row.rem_euclid(height as isize * 2),
+                Boundary::Twisted => {
+                    row = row.rem_euclid(height as isize * 2);
+                    col = (width as isize * 2 - 2) - col;
+                }
+            }
+        } else {
+            return Some((row as usize, col as usize));
+        }
     }
+    None
+}
 
-    Some((row, col))
+// Cell next to the cell at (`row`, `col`) in `direction`, and whether getting
+// there crosses a twisted border
+pub(crate) fn get_adjacent_cell(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    row: usize,
+    col: usize,
+    direction: Direction,
+) -> Option<((usize, usize), bool)> {
+    let (d_row, d_col) = direction.offset();
+    let lattice_row = (row * 2) as isize + d_row * 2;
+    let lattice_col = (col * 2) as isize + d_col * 2;
+    let (next_row, next_col) = normalize_coord(width, height, topology, lattice_row, lattice_col)?;
+    let outside = |coord: isize, size: usize| coord < 0 || coord > (size as isize - 1) * 2;
+    let mirrored = (topology.horizontal == Boundary::Twisted && outside(lattice_col, width))
+        || (topology.vertical == Boundary::Twisted && outside(lattice_row, height));
+    Some(((next_row / 2, next_col / 2), mirrored))
 }
 
-fn _get_cell_coord(width: usize, height: usize, mut id: usize) -> Option<(usize, usize)> {
-    let row = 2 * (id / width);
-    id %= width;
-    let col = id * 2;
+pub(crate) fn get_edge_coord(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    mut id: usize,
+) -> Option<(usize, usize)> {
+    let row_edges = get_row_edge_count(width, topology);
+    let mut row = 2 * (id / (row_edges + width));
+    id %= row_edges + width;
+
+    let col = if id >= row_edges {
+        row += 1;
+        id -= row_edges;
+        id * 2
+    } else {
+        id * 2 + 1
+    };
 
-    if (row >= height * 2 - 1) || (col >= width * 2 - 1) {
-        println!("Given row {} or col {} too large", row, col);
+    let (rows, cols) = get_lattice_size(width, height, topology);
+    if (row >= rows) || (col >= cols) {
         return None;
     }
 
     Some((row, col))
 }
 
-fn get_edge_id(width: usize, height: usize, row: usize, col: usize) -> Option<usize> {
-    if !((row % 2 == 0) ^ (col % 2 == 0)) {
-        println!("Given row {} or col {} is not edge", row, col);
+pub(crate) fn get_edge_id(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    row: usize,
+    col: usize,
+) -> Option<usize> {
+    if row % 2 == col % 2 {
         return None;
     }
 
-    if (row >= height * 2 - 1) || (col >= width * 2 - 1) {
-        println!("Given row {} or col {} too large", row, col);
+    let (rows, cols) = get_lattice_size(width, height, topology);
+    if (row >= rows) || (col >= cols) {
         return None;
     }
 
-    let vertical_edges = ((row + 1) / 2 * (width - 1)) + (((row + 1) % 2) * col / 2);
-    let horizontal_edges = (row / 2 * width) + ((row % 2) * col / 2);
-    Some(vertical_edges + horizontal_edges)
+    let row_edges = get_row_edge_count(width, topology);
+    Some((row / 2) * (row_edges + width) + (row % 2) * row_edges + col / 2)
 }
 
 fn get_cell_id(width: usize, height: usize, row: usize, col: usize) -> Option<usize> {
-    if (row % 2 != 0) || (col % 2 != 0) {
-        println!("Given row {} or col {} is not edge", row, col);
+    if row % 2 == 1 || col % 2 == 1 {
         return None;
     }
 
     if (row >= height * 2 - 1) || (col >= width * 2 - 1) {
-        println!("Given row {} or col {} too large", row, col);
         return None;
     }
 
     Some(row / 2 * width + col / 2)
 }
 
+// Candidate edges grouped by symmetry, each flagged if it is a single edge
+// whose two cells are images of each other. Only one of those may be removed
+// for the maze to stay a tree.
+fn get_orbits(
+    width: usize,
+    height: usize,
+    symmetry: Symmetry,
+    candidates: &[bool],
+) -> Option<(Vec<Vec<usize>>, Vec<bool>)> {
+    let topology = Topology::PLANE;
+    let mut orbits = Vec::new();
+    let mut exclusive = Vec::new();
+    let mut assigned = HashSet::new();
+    for id in (0..candidates.len()).filter(|&id| candidates[id]) {
+        if assigned.contains(&id) {
+            continue;
+        }
+        let edge = get_edge(width, height, topology, id)?;
+        let mut orbit = Vec::new();
+        for image in symmetry.get_edge_images(width, height, edge) {
+            let image_id = get_constrained_edge_id(width, height, topology, image)?;
+            assigned.insert(image_id);
+            orbit.push(image_id);
+        }
+        let (cell_a, cell_b) = get_adjacent_cells(width, height, topology, id)?;
+        let (near, far) = (
+            (cell_a / width, cell_a % width),
+            (cell_b / width, cell_b % width),
+        );
+        let swapped = symmetry.get_cell_images(width, height, near).contains(&far);
+        exclusive.push(orbit.len() == 1 && swapped);
+        orbits.push(orbit);
+    }
+    Some((orbits, exclusive))
+}
+
+// Wall with the given edge ID, addressed by the cell on its west or north side
+fn get_edge(width: usize, height: usize, topology: Topology, id: usize) -> Option<Edge> {
+    let (row, col) = get_edge_coord(width, height, topology, id)?;
+    if row % 2 == 0 {
+        Some(Edge::East(row / 2, col / 2))
+    } else {
+        Some(Edge::South(row / 2, col / 2))
+    }
+}
+
+// Edge ID of a wall given by the cell on its west or north side
+pub(crate) fn get_constrained_edge_id(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    edge: Edge,
+) -> Option<usize> {
+    let (row, col) = match edge {
+        Edge::East(row, col) => (row * 2, col * 2 + 1),
+        Edge::South(row, col) => (row * 2 + 1, col * 2),
+    };
+    get_edge_id(width, height, topology, row, col)
+}
+
+// Cells on either side of an edge
+pub(crate) fn get_adjacent_cells(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    id: usize,
+) -> Option<(usize, usize)> {
+    let (row, col) = get_edge_coord(width, height, topology, id)?;
+    let (row, col) = (row as isize, col as isize);
+    let (a, b) = if row % 2 == 0 {
+        ((row, col - 1), (row, col + 1))
+    } else {
+        ((row - 1, col), (row + 1, col))
+    };
+    let (a_row, a_col) = normalize_coord(width, height, topology, a.0, a.1)?;
+    let (b_row, b_col) = normalize_coord(width, height, topology, b.0, b.1)?;
+    let cell_a = get_cell_id(width, height, a_row, a_col)?;
+    let cell_b = get_cell_id(width, height, b_row, b_col)?;
+    Some((cell_a, cell_b))
+}
+
+// Edges meeting the given edge at each of its two posts, ordered as in the
+// `WallWeights` diagram. A post on a closed border has no neighbors.
+fn get_neighbor_groups(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    id: usize,
+) -> Option<(Option<NeighborsOneSided>, Option<NeighborsOneSided>)> {
+    let (row, col) = get_edge_coord(width, height, topology, id)?;
+    let (row, col) = (row as isize, col as isize);
+    let (first, second) = if row % 2 == 0 {
+        (
+            [(row - 1, col - 1), (row - 2, col), (row - 1, col + 1)],
+            [(row + 1, col - 1), (row + 2, col), (row + 1, col + 1)],
+        )
+    } else {
+        (
+            [(row - 1, col - 1), (row, col - 2), (row + 1, col - 1)],
+            [(row - 1, col + 1), (row, col + 2), (row + 1, col + 1)],
+        )
+    };
+    let to_group =
+        |post: (isize, isize), group: [(isize, isize); 3]| -> Option<Option<NeighborsOneSided>> {
+            if normalize_coord(width, height, topology, post.0, post.1).is_none() {
+                return Some(None);
+            }
+            let mut ids = [0; 3];
+            for (id, (r, c)) in ids.iter_mut().zip(group) {
+                let (r, c) = normalize_coord(width, height, topology, r, c)?;
+                *id = get_edge_id(width, height, topology, r, c)?;
+            }
+            Some(Some((ids[0], ids[1], ids[2])))
+        };
+
+    let (first_post, second_post) = if row % 2 == 0 {
+        ((row - 1, col), (row + 1, col))
+    } else {
+        ((row, col - 1), (row, col + 1))
+    };
+    Some((to_group(first_post, first)?, to_group(second_post, second)?))
+}
+
+// Edges sharing a post with the given edge
+pub(crate) fn get_neighbor_edges(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    id: usize,
+) -> Option<Vec<usize>> {
+    let (first, second) = get_neighbor_groups(width, height, topology, id)?;
+    Some(
+        [first, second]
+            .into_iter()
+            .flatten()
+            .flat_map(|(n1, n2, n3)| [n1, n2, n3])
+            .collect(),
+    )
+}
+
+// The square lattice seen by the generator
+pub(crate) struct SquareGrid<'a> {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) topology: Topology,
+    pub(crate) config: &'a WallWeights,
+}
+
+impl WallGraph for SquareGrid<'_> {
+    fn edge_count(&self) -> usize {
+        get_edge_count(self.width, self.height, self.topology)
+    }
+
+    fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)> {
+        get_adjacent_cells(self.width, self.height, self.topology, id)
+    }
+
+    fn neighbor_edges(&self, id: usize) -> Vec<usize> {
+        get_neighbor_edges(self.width, self.height, self.topology, id).unwrap_or_default()
+    }
+
+    fn weight(&self, edges: &[bool], id: usize) -> Option<u32> {
+        get_weight(
+            self.width,
+            self.height,
+            self.topology,
+            edges,
+            self.config,
+            id,
+        )
+    }
+}
+
 // ========== Wall Weights ==========
 
+#[derive(Debug, Clone)]
 pub struct WallWeights {
     // Name Format: type[0][1][2][3]_[4][5][6]
     //     0   3
@@ -362,8 +1290,10 @@ pub struct WallWeights {
     pub type_000x000: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum WallType {
+// Shape of the walls around a wall, named after the `WallWeights` field
+// giving its weight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WallType {
     Type111x111,
     Type111x011,
     Type111x101,
@@ -391,734 +1321,570 @@ enum WallType {
 }
 
 type NeighborsOneSided = (usize, usize, usize);
-type NeighborsTwoSided = (usize, usize, usize, usize, usize, usize);
 
-fn get_weight(
-    width: usize,
-    height: usize,
-    edges: &Vec<bool>,
-    config: &WallWeights,
-    id: usize,
-) -> Option<u32> {
-    match get_wall_type(width, height, edges, id)? {
-        WallType::Type111x111 => Some(config.type_111x111),
-        WallType::Type111x011 => Some(config.type_111x011),
-        WallType::Type111x101 => Some(config.type_111x101),
-        WallType::Type111x100 => Some(config.type_111x100),
-        WallType::Type111x010 => Some(config.type_111x010),
-        WallType::Type111x000 => Some(config.type_111x000),
-        WallType::Type101x101 => Some(config.type_101x101),
-        WallType::Type101x011 => Some(config.type_101x011),
-        WallType::Type101x010 => Some(config.type_101x010),
-        WallType::Type101x001 => Some(config.type_101x001),
-        WallType::Type101x000 => Some(config.type_101x000),
-        WallType::Type011x011 => Some(config.type_011x011),
-        WallType::Type011x110 => Some(config.type_011x110),
-        WallType::Type011x010 => Some(config.type_011x010),
-        WallType::Type011x001 => Some(config.type_011x001),
-        WallType::Type011x100 => Some(config.type_011x100),
-        WallType::Type011x000 => Some(config.type_011x000),
-        WallType::Type010x010 => Some(config.type_010x010),
-        WallType::Type010x100 => Some(config.type_010x100),
-        WallType::Type010x000 => Some(config.type_010x000),
-        WallType::Type001x001 => Some(config.type_001x001),
-        WallType::Type001x100 => Some(config.type_001x100),
-        WallType::Type001x000 => Some(config.type_001x000),
-        WallType::Type000x000 => Some(config.type_000x000),
+impl WallType {
+    // Every wall type, in the order of the `WallWeights` fields
+    pub const ALL: [WallType; 24] = [
+        WallType::Type111x111,
+        WallType::Type111x011,
+        WallType::Type111x101,
+        WallType::Type111x100,
+        WallType::Type111x010,
+        WallType::Type111x000,
+        WallType::Type101x101,
+        WallType::Type101x011,
+        WallType::Type101x010,
+        WallType::Type101x001,
+        WallType::Type101x000,
+        WallType::Type011x011,
+        WallType::Type011x110,
+        WallType::Type011x010,
+        WallType::Type011x001,
+        WallType::Type011x100,
+        WallType::Type011x000,
+        WallType::Type010x010,
+        WallType::Type010x100,
+        WallType::Type010x000,
+        WallType::Type001x001,
+        WallType::Type001x100,
+        WallType::Type001x000,
+        WallType::Type000x000,
+    ];
+
+    pub fn iter() -> impl Iterator<Item = WallType> {
+        Self::ALL.into_iter()
     }
-}
-
-fn get_wall_type(width: usize, height: usize, edges: &Vec<bool>, id: usize) -> Option<WallType> {
-    let (row, col) = get_edge_coord(width, height, id)?;
-
-    if row == 0 || row == (height * 2 - 2) || col == 0 || col == (width * 2 - 2) {
-        let neighbors;
-        if row == 0 {
-            let n1 = get_edge_id(width, height, row + 1, col - 1)?;
-            let n2 = get_edge_id(width, height, row + 2, col)?;
-            let n3 = get_edge_id(width, height, row + 1, col + 1)?;
-            neighbors = (n1, n2, n3);
-        } else if row == (height * 2 - 2) {
-            let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-            let n2 = get_edge_id(width, height, row - 2, col)?;
-            let n3 = get_edge_id(width, height, row - 1, col + 1)?;
-            neighbors = (n1, n2, n3);
-        } else if col == 0 {
-            let n1 = get_edge_id(width, height, row - 1, col + 1)?;
-            let n2 = get_edge_id(width, height, row, col + 2)?;
-            let n3 = get_edge_id(width, height, row + 1, col + 1)?;
-            neighbors = (n1, n2, n3);
-        } else if col == (width * 2 - 2) {
-            let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-            let n2 = get_edge_id(width, height, row, col - 2)?;
-            let n3 = get_edge_id(width, height, row + 1, col - 1)?;
-            neighbors = (n1, n2, n3);
-        } else {
-            println!("SHOULD NEVER REACH HERE!");
-            return None;
-        }
 
-        if contains_wall_type_111(edges, neighbors) {
-            return Some(WallType::Type111x000);
-        } else if contains_wall_type_101(edges, neighbors) {
-            return Some(WallType::Type101x000);
-        } else if contains_wall_type_011(edges, neighbors) {
-            return Some(WallType::Type011x000);
-        } else if contains_wall_type_010(edges, neighbors) {
-            return Some(WallType::Type010x000);
-        } else if contains_wall_type_001(edges, neighbors) {
-            return Some(WallType::Type001x000);
-        } else if contains_wall_type_000(edges, neighbors) {
-            return Some(WallType::Type000x000);
-        } else {
-            println!("Cannot find matching EDGE wall type");
-            return None;
+    // Neighbor mask the type is named after, holding one bit per neighbor
+    // from neighbor 0 of the `WallWeights` diagram in the highest of 6 bits
+    // down to neighbor 5 in the lowest, e.g. `0b011_001` for `Type011x001`.
+    // Every other mask of the type is a mirror image of this one.
+    pub const fn canonical_mask(self) -> u8 {
+        match self {
+            WallType::Type111x111 => 0b111_111,
+            WallType::Type111x011 => 0b111_011,
+            WallType::Type111x101 => 0b111_101,
+            WallType::Type111x100 => 0b111_100,
+            WallType::Type111x010 => 0b111_010,
+            WallType::Type111x000 => 0b111_000,
+            WallType::Type101x101 => 0b101_101,
+            WallType::Type101x011 => 0b101_011,
+            WallType::Type101x010 => 0b101_010,
+            WallType::Type101x001 => 0b101_001,
+            WallType::Type101x000 => 0b101_000,
+            WallType::Type011x011 => 0b011_011,
+            WallType::Type011x110 => 0b011_110,
+            WallType::Type011x010 => 0b011_010,
+            WallType::Type011x001 => 0b011_001,
+            WallType::Type011x100 => 0b011_100,
+            WallType::Type011x000 => 0b011_000,
+            WallType::Type010x010 => 0b010_010,
+            WallType::Type010x100 => 0b010_100,
+            WallType::Type010x000 => 0b010_000,
+            WallType::Type001x001 => 0b001_001,
+            WallType::Type001x100 => 0b001_100,
+            WallType::Type001x000 => 0b001_000,
+            WallType::Type000x000 => 0b000_000,
         }
     }
 
-    let neighbors;
-    if row % 2 == 0 {
-        let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-        let n2 = get_edge_id(width, height, row - 2, col)?;
-        let n3 = get_edge_id(width, height, row - 1, col + 1)?;
-        let n4 = get_edge_id(width, height, row + 1, col - 1)?;
-        let n5 = get_edge_id(width, height, row + 2, col)?;
-        let n6 = get_edge_id(width, height, row + 1, col + 1)?;
-        neighbors = (n1, n2, n3, n4, n5, n6);
-    } else {
-        let n1 = get_edge_id(width, height, row - 1, col - 1)?;
-        let n2 = get_edge_id(width, height, row, col - 2)?;
-        let n3 = get_edge_id(width, height, row + 1, col - 1)?;
-        let n4 = get_edge_id(width, height, row - 1, col + 1)?;
-        let n5 = get_edge_id(width, height, row, col + 2)?;
-        let n6 = get_edge_id(width, height, row + 1, col + 1)?;
-        neighbors = (n1, n2, n3, n4, n5, n6);
+    // Type of a wall given its neighbor mask, laid out as in
+    // `canonical_mask`. Only the lowest 6 bits are used.
+    pub fn from_mask(mask: u8) -> WallType {
+        WALL_TYPES[(mask & 0b111_111) as usize]
     }
 
-    if contains_wall_type_111x111(edges, neighbors) {
-        return Some(WallType::Type111x111);
-    } else if contains_wall_type_111x011(edges, neighbors) {
-        return Some(WallType::Type111x011);
-    } else if contains_wall_type_111x101(edges, neighbors) {
-        return Some(WallType::Type111x101);
-    } else if contains_wall_type_111x100(edges, neighbors) {
-        return Some(WallType::Type111x100);
-    } else if contains_wall_type_111x010(edges, neighbors) {
-        return Some(WallType::Type111x010);
-    } else if contains_wall_type_101x101(edges, neighbors) {
-        return Some(WallType::Type101x101);
-    } else if contains_wall_type_101x011(edges, neighbors) {
-        return Some(WallType::Type101x011);
-    } else if contains_wall_type_101x010(edges, neighbors) {
-        return Some(WallType::Type101x010);
-    } else if contains_wall_type_101x001(edges, neighbors) {
-        return Some(WallType::Type101x001);
-    } else if contains_wall_type_011x011(edges, neighbors) {
-        return Some(WallType::Type011x011);
-    } else if contains_wall_type_011x110(edges, neighbors) {
-        return Some(WallType::Type011x110);
-    } else if contains_wall_type_011x010(edges, neighbors) {
-        return Some(WallType::Type011x010);
-    } else if contains_wall_type_011x001(edges, neighbors) {
-        return Some(WallType::Type011x001);
-    } else if contains_wall_type_011x100(edges, neighbors) {
-        return Some(WallType::Type011x100);
-    } else if contains_wall_type_010x010(edges, neighbors) {
-        return Some(WallType::Type010x010);
-    } else if contains_wall_type_010x100(edges, neighbors) {
-        return Some(WallType::Type010x100);
-    } else if contains_wall_type_001x001(edges, neighbors) {
-        return Some(WallType::Type001x001);
-    } else if contains_wall_type_001x100(edges, neighbors) {
-        return Some(WallType::Type001x100);
-    } else if contains_wall_type_111x000(edges, neighbors) {
-        return Some(WallType::Type111x000);
-    } else if contains_wall_type_101x000(edges, neighbors) {
-        return Some(WallType::Type101x000);
-    } else if contains_wall_type_011x000(edges, neighbors) {
-        return Some(WallType::Type011x000);
-    } else if contains_wall_type_010x000(edges, neighbors) {
-        return Some(WallType::Type010x000);
-    } else if contains_wall_type_001x000(edges, neighbors) {
-        return Some(WallType::Type001x000);
-    } else if contains_wall_type_000x000(edges, neighbors) {
-        return Some(WallType::Type000x000);
+    // Type of a wall given whether each of its neighbors is a wall, ordered
+    // as in the `WallWeights` diagram. Three neighbors describe a wall
+    // touching a closed border, and are classified as if the missing side
+    // had no walls.
+    pub fn from_neighbors(walls: &[bool]) -> Option<WallType> {
+        if walls.len() != 3 && walls.len() != 6 {
+            return None;
+        }
+        let mask = walls
+            .iter()
+            .chain(&[false; 3][..6 - walls.len()])
+            .fold(0, |mask, &wall| (mask << 1) | u8::from(wall));
+        Some(WallType::from_mask(mask))
     }
-
-    println!("Cannot find matching MIXED wall type");
-    None
 }
 
-fn contains_wall_type_111x111(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x111
-    edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5]
+// Wall type of every neighbor mask, filled in from the mirror images of
+// every canonical mask
+const WALL_TYPES: [WallType; 64] = {
+    let mut table = [WallType::Type000x000; 64];
+    let mut k = 0;
+    while k < WallType::ALL.len() {
+        let wall_type = WallType::ALL[k];
+        let images = get_mirror_images(wall_type.canonical_mask());
+        let mut i = 0;
+        while i < images.len() {
+            table[images[i] as usize] = wall_type;
+            i += 1;
+        }
+        k += 1;
+    }
+    table
+};
+
+// Masks of the same walls flipped along the wall, across it, and both ways
+const fn get_mirror_images(mask: u8) -> [u8; 4] {
+    let first = (mask >> 3) & 0b111;
+    let second = mask & 0b111;
+    [
+        (first << 3) | second,
+        (reverse_side(first) << 3) | reverse_side(second),
+        (second << 3) | first,
+        (reverse_side(second) << 3) | reverse_side(first),
+    ]
 }
 
-fn contains_wall_type_111x011(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x011
-    let v1 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 111x110
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 011x111
-    let v3 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 110x111
-    let v4 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2 || v3 || v4
+const fn reverse_side(side: u8) -> u8 {
+    ((side & 0b001) << 2) | (side & 0b010) | ((side & 0b100) >> 2)
 }
 
-fn contains_wall_type_111x101(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x101
-    let v1 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 101x111
-    let v2 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
-}
+impl WallWeights {
+    // Weights given by a function of the wall type
+    pub fn from_fn<F>(mut weight: F) -> WallWeights
+    where
+        F: FnMut(WallType) -> u32,
+    {
+        WallWeights {
+            type_111x111: weight(WallType::Type111x111),
+            type_111x011: weight(WallType::Type111x011),
+            type_111x101: weight(WallType::Type111x101),
+            type_111x100: weight(WallType::Type111x100),
+            type_111x010: weight(WallType::Type111x010),
+            type_111x000: weight(WallType::Type111x000),
+            type_101x101: weight(WallType::Type101x101),
+            type_101x011: weight(WallType::Type101x011),
+            type_101x010: weight(WallType::Type101x010),
+            type_101x001: weight(WallType::Type101x001),
+            type_101x000: weight(WallType::Type101x000),
+            type_011x011: weight(WallType::Type011x011),
+            type_011x110: weight(WallType::Type011x110),
+            type_011x010: weight(WallType::Type011x010),
+            type_011x001: weight(WallType::Type011x001),
+            type_011x100: weight(WallType::Type011x100),
+            type_011x000: weight(WallType::Type011x000),
+            type_010x010: weight(WallType::Type010x010),
+            type_010x100: weight(WallType::Type010x100),
+            type_010x000: weight(WallType::Type010x000),
+            type_001x001: weight(WallType::Type001x001),
+            type_001x100: weight(WallType::Type001x100),
+            type_001x000: weight(WallType::Type001x000),
+            type_000x000: weight(WallType::Type000x000),
+        }
+    }
 
-fn contains_wall_type_111x100(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x100
-    let v1 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 111x001
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x111
-    let v3 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 001x111
-    let v4 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2 || v3 || v4
+    // Weight of the given wall type
+    pub fn get(&self, wall_type: WallType) -> u32 {
+        match wall_type {
+            WallType::Type111x111 => self.type_111x111,
+            WallType::Type111x011 => self.type_111x011,
+            WallType::Type111x101 => self.type_111x101,
+            WallType::Type111x100 => self.type_111x100,
+            WallType::Type111x010 => self.type_111x010,
+            WallType::Type111x000 => self.type_111x000,
+            WallType::Type101x101 => self.type_101x101,
+            WallType::Type101x011 => self.type_101x011,
+            WallType::Type101x010 => self.type_101x010,
+            WallType::Type101x001 => self.type_101x001,
+            WallType::Type101x000 => self.type_101x000,
+            WallType::Type011x011 => self.type_011x011,
+            WallType::Type011x110 => self.type_011x110,
+            WallType::Type011x010 => self.type_011x010,
+            WallType::Type011x001 => self.type_011x001,
+            WallType::Type011x100 => self.type_011x100,
+            WallType::Type011x000 => self.type_011x000,
+            WallType::Type010x010 => self.type_010x010,
+            WallType::Type010x100 => self.type_010x100,
+            WallType::Type010x000 => self.type_010x000,
+            WallType::Type001x001 => self.type_001x001,
+            WallType::Type001x100 => self.type_001x100,
+            WallType::Type001x000 => self.type_001x000,
+            WallType::Type000x000 => self.type_000x000,
+        }
+    }
 }
 
-fn contains_wall_type_111x010(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x010
-    let v1 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 010x111
-    let v2 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
+pub(crate) fn get_weight(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    edges: &[bool],
+    config: &WallWeights,
+    id: usize,
+) -> Option<u32> {
+    Some(config.get(get_wall_type(width, height, topology, edges, id)?))
 }
 
-fn contains_wall_type_101x101(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 101x101
-    edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5]
+// Weight of an edge given the state of its neighbors, ordered as in the
+// `WallWeights` diagram. Three neighbors describe an edge touching a border.
+pub(crate) fn get_weight_of_neighbors(config: &WallWeights, walls: &[bool]) -> Option<u32> {
+    Some(config.get(WallType::from_neighbors(walls)?))
 }
 
-fn contains_wall_type_101x011(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 101x011
-    let v1 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 101x110
-    let v2 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 011x101
-    let v3 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 110x101
-    let v4 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2 || v3 || v4
+fn get_wall_type(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    edges: &[bool],
+    id: usize,
+) -> Option<WallType> {
+    get_wall_type_with(width, height, topology, id, |id| edges.get(id).copied())
 }
 
-fn contains_wall_type_101x010(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 101x010
-    let v1 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 010x101
-    let v2 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
+// Wall type of an edge, looking up whether each neighbor is a wall through
+// `is_wall`
+fn get_wall_type_with<F>(
+    width: usize,
+    height: usize,
+    topology: Topology,
+    id: usize,
+    is_wall: F,
+) -> Option<WallType>
+where
+    F: Fn(usize) -> Option<bool>,
+{
+    let walls = match get_neighbor_groups(width, height, topology, id)? {
+        (Some(first), Some(second)) => vec![
+            is_wall(first.0)?,
+            is_wall(first.1)?,
+            is_wall(first.2)?,
+            is_wall(second.0)?,
+            is_wall(second.1)?,
+            is_wall(second.2)?,
+        ],
+        (Some(neighbors), None) | (None, Some(neighbors)) => {
+            vec![
+                is_wall(neighbors.0)?,
+                is_wall(neighbors.1)?,
+                is_wall(neighbors.2)?,
+            ]
+        }
+        (None, None) => return None,
+    };
+    WallType::from_neighbors(&walls)
 }
 
-fn contains_wall_type_101x001(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 101x001
-    let v1 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 101x100
-    let v2 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 001x101
-    let v3 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x101
-    let v4 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-fn contains_wall_type_011x011(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x011
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 110x110
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2
-}
+    use super::*;
 
-fn contains_wall_type_011x110(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x110
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 110x011
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
-}
+    // Neighbors of a mask in the order of the `WallWeights` diagram
+    fn to_walls(mask: u8, count: usize) -> Vec<bool> {
+        (0..count)
+            .map(|k| mask >> (count - 1 - k) & 1 == 1)
+            .collect()
+    }
 
-fn contains_wall_type_011x010(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x010
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 110x010
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 010x011
-    let v3 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 010x110
-    let v4 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    // The walls seen from the other end of the wall, swapping the two posts
+    fn flip_along(walls: &[bool]) -> Vec<bool> {
+        [&walls[3..], &walls[..3]].concat()
+    }
 
-fn contains_wall_type_011x001(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x001
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 110x100
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 001x011
-    let v3 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x110
-    let v4 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    // The walls seen from the other side of the wall, reversing the
+    // neighbors at each post
+    fn flip_across(walls: &[bool]) -> Vec<bool> {
+        let mut res = walls.to_vec();
+        res[..3].reverse();
+        res[3..].reverse();
+        res
+    }
 
-fn contains_wall_type_011x100(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x100
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 110x001
-    let v2 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x011
-    let v3 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 001x110
-    let v4 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    #[test]
+    fn every_two_sided_mask_has_one_type() {
+        let mut counts: HashMap<WallType, usize> = HashMap::new();
+        for mask in 0..64 {
+            let wall_type = WallType::from_neighbors(&to_walls(mask, 6)).unwrap();
+            assert_eq!(wall_type, WallType::from_mask(mask));
+            *counts.entry(wall_type).or_default() += 1;
+        }
+        assert_eq!(counts.len(), WallType::ALL.len());
+        assert_eq!(counts.values().sum::<usize>(), 64);
+    }
 
-fn contains_wall_type_010x010(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 010x010
-    !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5]
-}
+    #[test]
+    fn canonical_masks_are_distinct_and_round_trip() {
+        let mut masks = HashMap::new();
+        for wall_type in WallType::iter() {
+            let mask = wall_type.canonical_mask();
+            assert!(mask < 64);
+            assert_eq!(WallType::from_mask(mask), wall_type);
+            assert_eq!(masks.insert(mask, wall_type), None);
+        }
+        assert_eq!(WallType::iter().count(), 24);
+    }
 
-fn contains_wall_type_010x100(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 010x100
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 010x001
-    let v2 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x010
-    let v3 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 001x010
-    let v4 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    #[test]
+    fn canonical_masks_match_type_names() {
+        for wall_type in WallType::iter() {
+            let name = format!("{:?}", wall_type)
+                .replace("Type", "")
+                .replace('x', "");
+            assert_eq!(
+                u8::from_str_radix(&name, 2).unwrap(),
+                wall_type.canonical_mask()
+            );
+        }
+    }
 
-fn contains_wall_type_001x001(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 001x001
-    let v1 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x100
-    let v2 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2
-}
+    #[test]
+    fn two_sided_types_are_invariant_under_mirroring() {
+        for mask in 0..64 {
+            let walls = to_walls(mask, 6);
+            let wall_type = WallType::from_neighbors(&walls);
+            let images = [
+                flip_along(&walls),
+                flip_across(&walls),
+                flip_along(&flip_across(&walls)),
+            ];
+            for image in images {
+                assert_eq!(WallType::from_neighbors(&image), wall_type, "{:06b}", mask);
+            }
+        }
+    }
 
-fn contains_wall_type_001x100(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 001x100
-    let v1 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 100x001
-    let v2 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
-}
+    #[test]
+    fn two_sided_types_only_join_mirror_images() {
+        for a in 0..64 {
+            for b in 0..64 {
+                if WallType::from_mask(a) != WallType::from_mask(b) {
+                    continue;
+                }
+                let walls = to_walls(a, 6);
+                let images = [
+                    walls.clone(),
+                    flip_along(&walls),
+                    flip_across(&walls),
+                    flip_along(&flip_across(&walls)),
+                ];
+                assert!(images.contains(&to_walls(b, 6)), "{:06b} {:06b}", a, b);
+            }
+        }
+    }
 
-fn contains_wall_type_111x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 111x000
-    let v1 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x111
-    let v2 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
-}
+    #[test]
+    fn every_one_sided_mask_has_one_type() {
+        for mask in 0..8 {
+            let walls = to_walls(mask, 3);
+            let wall_type = WallType::from_neighbors(&walls).unwrap();
+            // The missing side counts as having no walls, on either side
+            assert_eq!(wall_type, WallType::from_mask(mask << 3));
+            assert_eq!(wall_type, WallType::from_mask(mask));
+
+            let reversed: Vec<bool> = walls.iter().rev().copied().collect();
+            assert_eq!(WallType::from_neighbors(&reversed), Some(wall_type));
+        }
+    }
 
-fn contains_wall_type_101x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 101x000
-    let v1 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x101
-    let v2 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    v1 || v2
-}
+    #[test]
+    fn one_sided_types_match_their_names() {
+        let expected = [
+            ([false, false, false], WallType::Type000x000),
+            ([false, false, true], WallType::Type001x000),
+            ([false, true, false], WallType::Type010x000),
+            ([false, true, true], WallType::Type011x000),
+            ([true, false, false], WallType::Type001x000),
+            ([true, false, true], WallType::Type101x000),
+            ([true, true, false], WallType::Type011x000),
+            ([true, true, true], WallType::Type111x000),
+        ];
+        for (walls, wall_type) in expected {
+            assert_eq!(WallType::from_neighbors(&walls), Some(wall_type));
+        }
+    }
 
-fn contains_wall_type_011x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 011x000
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x011
-    let v2 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && edges[neighbors.5];
-    // 110x000
-    let v3 = edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x110
-    let v4 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    #[test]
+    fn readme_example_is_one_type() {
+        assert_eq!(WallType::from_mask(0b100_101), WallType::Type101x001);
+    }
 
-fn contains_wall_type_010x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 010x000
-    let v1 = !edges[neighbors.0]
-        && edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x010
-    let v2 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2
-}
+    #[test]
+    fn wrong_neighbor_counts_are_rejected() {
+        assert_eq!(WallType::from_neighbors(&[]), None);
+        assert_eq!(WallType::from_neighbors(&[true; 4]), None);
+        assert_eq!(WallType::from_neighbors(&[true; 7]), None);
+    }
 
-fn contains_wall_type_001x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    // 001x000
-    let v1 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x001
-    let v2 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && edges[neighbors.5];
-    // 100x000
-    let v3 = edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    // 000x100
-    let v4 = !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5];
-    v1 || v2 || v3 || v4
-}
+    #[test]
+    fn maze_edges_are_classified_from_their_neighbors() {
+        let mut grid = vec![vec![true; 7]; 7];
+        for row in [1, 3, 5] {
+            for col in [1, 3, 5] {
+                grid[row][col] = false;
+            }
+        }
+        let maze = Maze {
+            width: 3,
+            height: 3,
+            grid,
+            topology: Topology::default(),
+            crossings: Vec::new(),
+        };
+        assert_eq!(
+            maze.classify_edge(Edge::East(1, 0)),
+            Some(WallType::Type111x111)
+        );
+        assert_eq!(
+            maze.classify_edge(Edge::East(0, 0)),
+            Some(WallType::Type111x000)
+        );
+        assert_eq!(maze.classify_edge(Edge::East(0, 2)), None);
+    }
 
-fn contains_wall_type_000x000(edges: &Vec<bool>, neighbors: NeighborsTwoSided) -> bool {
-    !edges[neighbors.0]
-        && !edges[neighbors.1]
-        && !edges[neighbors.2]
-        && !edges[neighbors.3]
-        && !edges[neighbors.4]
-        && !edges[neighbors.5]
-}
+    // `100` and `110` on a closed border are the mirror images of `001` and
+    // `011`, which the original classifier had swapped
+    #[test]
+    fn border_edges_are_classified_by_mirror_image() {
+        let mut grid = vec![vec![true; 7]; 7];
+        for row in [1, 3, 5] {
+            for col in [1, 3, 5] {
+                grid[row][col] = false;
+            }
+        }
+        // Open the walls below (0, 1) and right of (1, 0), which leaves only
+        // the wall below (0, 0) at the lower end of the wall right of (0, 0)
+        grid[2][3] = false;
+        grid[3][2] = false;
+        let mut maze = Maze {
+            width: 3,
+            height: 3,
+            grid,
+            topology: Topology::default(),
+            crossings: Vec::new(),
+        };
+        assert_eq!(
+            maze.classify_edge(Edge::East(0, 0)),
+            Some(WallType::Type001x000)
+        );
+
+        maze.grid[3][2] = true;
+        assert_eq!(
+            maze.classify_edge(Edge::East(0, 0)),
+            Some(WallType::Type011x000)
+        );
+    }
 
-fn contains_wall_type_111(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    // 111x000 and 000x111
-    edges[neighbors.0] && edges[neighbors.1] && edges[neighbors.2]
-}
+    #[test]
+    fn weights_follow_wall_types() {
+        let config = WallWeights {
+            type_111x111: 1,
+            type_111x011: 2,
+            type_111x101: 3,
+            type_111x100: 4,
+            type_111x010: 5,
+            type_111x000: 6,
+            type_101x101: 7,
+            type_101x011: 8,
+            type_101x010: 9,
+            type_101x001: 10,
+            type_101x000: 11,
+            type_011x011: 12,
+            type_011x110: 13,
+            type_011x010: 14,
+            type_011x001: 15,
+            type_011x100: 16,
+            type_011x000: 17,
+            type_010x010: 18,
+            type_010x100: 19,
+            type_010x000: 20,
+            type_001x001: 21,
+            type_001x100: 22,
+            type_001x000: 23,
+            type_000x000: 24,
+        };
+        for (k, wall_type) in WallType::iter().enumerate() {
+            let walls = to_walls(wall_type.canonical_mask(), 6);
+            assert_eq!(get_weight_of_neighbors(&config, &walls), Some(k as u32 + 1));
+        }
+    }
 
-fn contains_wall_type_101(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    // 101x000 and 000x101
-    edges[neighbors.0] && !edges[neighbors.1] && edges[neighbors.2]
-}
+    #[test]
+    fn non_finite_weave_densities_are_rejected() {
+        for weave_density in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let options = BuildOptions {
+                weave_density,
+                ..Default::default()
+            };
+            let res = Maze::build_with_options(4, 4, WallWeights::from_fn(|_| 1), options);
+            assert!(matches!(res, Err(BuildError::InvalidWeaveDensity)));
+        }
+    }
 
-fn contains_wall_type_011(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    // 011x000 and 000x011
-    let v1 = !edges[neighbors.0] && edges[neighbors.1] && edges[neighbors.2];
-    // 110x000 and 000x110
-    let v2 = edges[neighbors.0] && !edges[neighbors.1] && !edges[neighbors.2];
-    v1 || v2
-}
+    // Raster position of the image of a raster position
+    fn get_raster_image(maze: &Maze, symmetry: Symmetry, (i, j): (usize, usize)) -> (usize, usize) {
+        let (rows, cols) = (maze.height * 2, maze.width * 2);
+        match symmetry {
+            Symmetry::None => (i, j),
+            Symmetry::HalfTurn => (rows - i, cols - j),
+            Symmetry::QuarterTurn => (j, cols - i),
+            Symmetry::MirrorLeftRight => (i, cols - j),
+            Symmetry::MirrorTopBottom => (rows - i, j),
+        }
+    }
 
-fn contains_wall_type_010(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    // 010x000 and 000x010
-    !edges[neighbors.0] && edges[neighbors.1] && !edges[neighbors.2]
-}
+    #[test]
+    fn symmetric_mazes_report_their_loops() {
+        let cases = [
+            (Symmetry::HalfTurn, 7, 5, 0),
+            (Symmetry::HalfTurn, 6, 4, 1),
+            (Symmetry::QuarterTurn, 7, 7, 0),
+            (Symmetry::QuarterTurn, 6, 6, 1),
+            (Symmetry::MirrorLeftRight, 6, 5, 0),
+            (Symmetry::MirrorLeftRight, 7, 4, 0),
+            (Symmetry::MirrorTopBottom, 5, 6, 0),
+            (Symmetry::MirrorTopBottom, 4, 7, 0),
+        ];
+        for (symmetry, width, height, expected) in cases {
+            for seed in 0..20 {
+                let options = BuildOptions {
+                    symmetry,
+                    seed: Some(seed),
+                    ..Default::default()
+                };
+                let mut reported = Vec::new();
+                let config = WallWeights::from_fn(|_| 1);
+                let maze = Maze::build_with_events(width, height, config, options, |event| {
+                    if let GenerationEvent::LoopsCreated { count } = event {
+                        reported.push(count);
+                    }
+                })
+                .unwrap();
 
-fn contains_wall_type_001(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    // 001x000 and 000x001
-    let v1 = !edges[neighbors.0] && !edges[neighbors.1] && edges[neighbors.2];
-    // 100x000 and 000x100
-    let v2 = edges[neighbors.0] && edges[neighbors.1] && !edges[neighbors.2];
-    v1 || v2
-}
+                for (i, row) in maze.grid.iter().enumerate() {
+                    for (j, &wall) in row.iter().enumerate() {
+                        let (r, c) = get_raster_image(&maze, symmetry, (i, j));
+                        assert_eq!(maze.grid[r][c], wall, "{:?} at ({}, {})", symmetry, i, j);
+                    }
+                }
+                // Every passage beyond the `width * height - 1` of a tree
+                // closes a loop
+                let passages = maze
+                    .grid
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &w)| (i, j, w)))
+                    .filter(|&(i, j, wall)| (i + j) % 2 == 1 && !wall)
+                    .count();
+                assert_eq!(passages, width * height - 1 + expected, "{:?}", symmetry);
+                assert_eq!(reported, vec![expected], "{:?}", symmetry);
+            }
+        }
+    }
 
-fn contains_wall_type_000(edges: &Vec<bool>, neighbors: NeighborsOneSided) -> bool {
-    !edges[neighbors.0] && !edges[neighbors.1] && !edges[neighbors.2]
+    #[test]
+    fn only_symmetric_mazes_report_loops() {
+        let mut reported = false;
+        let options = BuildOptions::default();
+        Maze::build_with_events(5, 5, WallWeights::from_fn(|_| 1), options, |event| {
+            reported |= matches!(event, GenerationEvent::LoopsCreated { .. });
+        })
+        .unwrap();
+        assert!(!reported);
+    }
 }
@@ -0,0 +1,257 @@
+use disjoint_sets::UnionFind;
+
+use crate::kruskal::{self, WallGraph};
+use crate::mask::CellMask;
+use crate::maze::{
+    build_grid, get_adjacent_cells, get_edge_count, get_neighbor_edges, get_weight, Maze, Topology,
+    WallWeights,
+};
+
+// A stack of `width` x `height` mazes joined by ladders. Level 0 is the
+// bottom level; a ladder joins a cell to the cell directly above it.
+#[derive(Debug)]
+pub struct Maze3d {
+    pub width: usize,
+    pub height: usize,
+    pub levels: Vec<Maze>,
+    // Whether each cell of every level but the top one has a ladder up
+    links: Vec<bool>,
+}
+
+impl Maze3d {
+    // Walls within a level are weighted with `config`, while every ladder
+    // between two levels is weighted with `link_weight`. `None` if the
+    // weights leave some cells out of reach.
+    pub fn build(
+        width: usize,
+        height: usize,
+        levels: usize,
+        config: WallWeights,
+        link_weight: u32,
+    ) -> Option<Maze3d> {
+        if width < 2 || height < 2 || levels < 1 || (levels > 1 && link_weight == 0) {
+            return None;
+        }
+
+        let graph = LayeredGrid {
+            width,
+            height,
+            levels,
+            config: &config,
+            link_weight,
+        };
+        let number_of_edges = graph.edge_count();
+        let mut edges = vec![true; number_of_edges];
+        let mut cells = UnionFind::<usize>::new(width * height * levels);
+        kruskal::carve(
+            &graph,
+            &mut edges,
+            &mut cells,
+            &vec![true; number_of_edges],
+            &mut rand::thread_rng(),
+            &mut |_, _| {},
+        )?;
+
+        // Walls with a weight of 0 are never removed, which may leave cells
+        // out of reach
+        let cell_count = width * height * levels;
+        if (1..cell_count).any(|cell| !cells.equiv(0, cell)) {
+            return None;
+        }
+
+        let layer_edges = get_edge_count(width, height, Topology::PLANE);
+        let mask = CellMask::new(width, height);
+        let mazes = edges[..layer_edges * levels]
+            .chunks(layer_edges)
+            .map(|layer| {
+                Some(Maze {
+                    width,
+                    height,
+                    grid: build_grid(width, height, Topology::PLANE, layer, &mask)?,
+                    topology: Topology::PLANE,
+                    crossings: Vec::new(),
+                })
+            })
+            .collect::<Option<Vec<Maze>>>()?;
+        let links = edges[layer_edges * levels..]
+            .iter()
+            .map(|&wall| !wall)
+            .collect();
+
+        Some(Maze3d {
+            width,
+            height,
+            levels: mazes,
+            links,
+        })
+    }
+
+    // Whether there is a ladder from (`level`, `row`, `col`) to the level above
+    pub fn has_link_up(&self, level: usize, row: usize, col: usize) -> bool {
+        if level + 1 >= self.levels.len() || row >= self.height || col >= self.width {
+            return false;
+        }
+        self.links[(level * self.height + row) * self.width + col]
+    }
+
+    // Whether there is a ladder from (`level`, `row`, `col`) to the level below
+    pub fn has_link_down(&self, level: usize, row: usize, col: usize) -> bool {
+        level > 0 && self.has_link_up(level - 1, row, col)
+    }
+
+    // Render every level side by side, bottom level first. Cells with a
+    // ladder are marked `UP`, `DN`, or `UD` when they lead both ways.
+    pub fn to_ascii(&self) -> String {
+        let mut res = String::new();
+        for i in 0..self.height * 2 + 1 {
+            let mut line = String::new();
+            for (level, maze) in self.levels.iter().enumerate() {
+                if level > 0 {
+                    line.push_str("  ");
+                }
+                for (j, &wall) in maze.grid[i].iter().enumerate() {
+                    let is_cell = i % 2 == 1 && j % 2 == 1;
+                    let (up, down) = if is_cell {
+                        let (row, col) = (i / 2, j / 2);
+                        (
+                            self.has_link_up(level, row, col),
+                            self.has_link_down(level, row, col),
+                        )
+                    } else {
+                        (false, false)
+                    };
+                    line.push_str(match (wall, up, down) {
+                        (true, _, _) => "##",
+                        (false, true, true) => "UD",
+                        (false, true, false) => "UP",
+                        (false, false, true) => "DN",
+                        (false, false, false) => "..",
+                    });
+                }
+            }
+            res.push_str(&line);
+            res.push('\n');
+        }
+        res
+    }
+
+    pub fn print(&self) {
+        print!("{}", self.to_ascii());
+    }
+}
+
+// ========== Layered Edge IDs ==========
+//
+// Edges are numbered level by level using the square grid numbering, followed
+// by the ladders between each pair of levels numbered by the lower cell.
+
+struct LayeredGrid<'a> {
+    width: usize,
+    height: usize,
+    levels: usize,
+    config: &'a WallWeights,
+    link_weight: u32,
+}
+
+impl LayeredGrid<'_> {
+    fn layer_edge_count(&self) -> usize {
+        get_edge_count(self.width, self.height, Topology::PLANE)
+    }
+
+    fn layer_cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    // Level and in-level ID of an edge, or `None` for a ladder
+    fn get_layer_edge(&self, id: usize) -> Option<(usize, usize)> {
+        let layer_edges = self.layer_edge_count();
+        if id < layer_edges * self.levels {
+            Some((id / layer_edges, id % layer_edges))
+        } else {
+            None
+        }
+    }
+}
+
+impl WallGraph for LayeredGrid<'_> {
+    fn edge_count(&self) -> usize {
+        self.layer_edge_count() * self.levels + self.layer_cell_count() * (self.levels - 1)
+    }
+
+    fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)> {
+        let cells = self.layer_cell_count();
+        match self.get_layer_edge(id) {
+            Some((level, local)) => {
+                let (a, b) = get_adjacent_cells(self.width, self.height, Topology::PLANE, local)?;
+                Some((level * cells + a, level * cells + b))
+            }
+            None => {
+                let lower = id - self.layer_edge_count() * self.levels;
+                Some((lower, lower + cells))
+            }
+        }
+    }
+
+    fn neighbor_edges(&self, id: usize) -> Vec<usize> {
+        // Ladders do not take part in the wall type of any edge
+        match self.get_layer_edge(id) {
+            Some((level, local)) => {
+                get_neighbor_edges(self.width, self.height, Topology::PLANE, local)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|n| level * self.layer_edge_count() + n)
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn weight(&self, edges: &[bool], id: usize) -> Option<u32> {
+        match self.get_layer_edge(id) {
+            Some((level, local)) => {
+                let layer_edges = self.layer_edge_count();
+                let layer = &edges[level * layer_edges..(level + 1) * layer_edges];
+                get_weight(
+                    self.width,
+                    self.height,
+                    Topology::PLANE,
+                    layer,
+                    self.config,
+                    local,
+                )
+            }
+            None => Some(self.link_weight),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_cell_is_reachable() {
+        let maze = Maze3d::build(4, 3, 3, WallWeights::from_fn(|_| 1), 1).unwrap();
+        let ladders = (0..2)
+            .flat_map(|level| (0..3).flat_map(move |row| (0..4).map(move |col| (level, row, col))))
+            .filter(|&(level, row, col)| maze.has_link_up(level, row, col))
+            .count();
+        let open_walls: usize = maze
+            .levels
+            .iter()
+            .map(|level| {
+                level
+                    .edges()
+                    .filter(|&e| level.wall(e) == Some(false))
+                    .count()
+            })
+            .sum();
+        // A tree over all 36 cells has 35 openings
+        assert_eq!(ladders + open_walls, 4 * 3 * 3 - 1);
+    }
+
+    #[test]
+    fn zero_weights_are_rejected() {
+        assert!(Maze3d::build(3, 3, 2, WallWeights::from_fn(|_| 0), 1).is_none());
+    }
+}
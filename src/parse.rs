@@ -0,0 +1,458 @@
+use std::fmt;
+
+use crate::maze::{Axis, Boundary, Crossing, Maze, Topology};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    // The text does not hold any maze
+    Empty,
+    // The maze must be at least 2x2 and end with a full row of posts
+    InvalidSize,
+    // A post is missing, or found where a post does not belong. Lines and
+    // columns count from 1 after removing indentation.
+    MisplacedPost {
+        line: usize,
+        column: usize,
+    },
+    // A character that does not belong at its position
+    UnexpectedCharacter {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "text does not hold a maze"),
+            ParseError::InvalidSize => {
+                write!(f, "maze must be at least 2x2 and closed at the bottom")
+            }
+            ParseError::MisplacedPost { line, column } => {
+                write!(f, "misplaced post at line {}, column {}", line, column)
+            }
+            ParseError::UnexpectedCharacter {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "unexpected character {:?} at line {}, column {}",
+                found, line, column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Settings for `Maze::from_ascii_with_options`
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    // Work out how opposite borders are joined from the openings in the
+    // outer wall, instead of reading the maze as a closed plane
+    pub infer_topology: bool,
+}
+
+impl Maze {
+    // Parse a maze drawn as text, either in the `+---+` layout used by
+    // micromouse contest sites (`o` posts are accepted as well) or in the
+    // `##`/`..` layout of `print`, e.g.
+    //
+    // +---+---+---+
+    // |       |   |
+    // +   +---+   +
+    // |           |
+    // +---+---+---+
+    //
+    // Anything inside a cell, such as a number or a letter, is ignored,
+    // except for a cell filled with `#`, which is left out of the maze.
+    // Crossings are only read back from the `print` layout. The maze is read
+    // as a closed plane, and openings in the outer wall, such as an entrance
+    // and an exit, are only kept in `grid`.
+    pub fn from_ascii(text: &str) -> Result<Maze, ParseError> {
+        Self::from_ascii_with_options(text, ParseOptions::default())
+    }
+
+    // Same as `from_ascii`. With `ParseOptions::infer_topology`, openings in
+    // the outer wall join opposite borders: straight across when they line
+    // up, and upside down when they mirror each other. Other openings leave
+    // the borders closed.
+    pub fn from_ascii_with_options(text: &str, options: ParseOptions) -> Result<Maze, ParseError> {
+        let lines = get_lines(text);
+        let first = lines.first().ok_or(ParseError::Empty)?;
+        let (grid, crossings) = if first.first() == Some(&'#') {
+            parse_raster(&lines)?
+        } else {
+            parse_classic(&lines)?
+        };
+        Ok(from_grid(grid, crossings, options.infer_topology))
+    }
+}
+
+// Lines holding the maze, without surrounding blank lines, indentation or
+// trailing whitespace
+fn get_lines(text: &str) -> Vec<Vec<char>> {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let Some(start) = lines.iter().position(|line| !line.is_empty()) else {
+        return Vec::new();
+    };
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .unwrap_or(start);
+    let lines = &lines[start..=end];
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.chars().skip(indent).collect())
+        .collect()
+}
+
+fn parse_classic(lines: &[Vec<char>]) -> Result<(Vec<Vec<bool>>, Vec<Crossing>), ParseError> {
+    let is_post = |c: char| c == '+' || c == 'o';
+    let first = &lines[0];
+    if !first.first().copied().is_some_and(is_post) {
+        return Err(unexpected(lines, 0, 0));
+    }
+    let cell_width = first[1..]
+        .iter()
+        .position(|&c| is_post(c))
+        .ok_or(ParseError::InvalidSize)?;
+    if cell_width == 0 {
+        return Err(ParseError::MisplacedPost { line: 1, column: 2 });
+    }
+    let step = cell_width + 1;
+    let length = first.len();
+    let width = (length - 1) / step;
+    if width * step + 1 != length {
+        return Err(ParseError::MisplacedPost {
+            line: 1,
+            column: length,
+        });
+    }
+    if width < 2 || lines.len() < 5 || lines.len() % 2 != 1 {
+        return Err(ParseError::InvalidSize);
+    }
+
+    let mut grid = vec![vec![true; width * 2 + 1]; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        if line.len() > length {
+            return Err(unexpected(lines, i, length));
+        }
+        let at = |column: usize| line.get(column).copied().unwrap_or(' ');
+        for column in 0..length {
+            let c = at(column);
+            let j = column / step * 2 + usize::from(column % step != 0);
+            match (i % 2, column % step) {
+                // Posts
+                (0, 0) if is_post(c) => {}
+                (0, 0) => return Err(misplaced_post(i, column)),
+                (_, _) if is_post(c) => return Err(misplaced_post(i, column)),
+                // Walls between two cells of a column
+                (0, _) => {
+                    let segment = column - column % step + 1;
+                    match (c, at(segment)) {
+                        ('-', '-') => {}
+                        (' ', ' ') => grid[i][j] = false,
+                        _ => return Err(unexpected(lines, i, column)),
+                    }
+                }
+                // Walls between two cells of a row
+                (_, 0) => match c {
+                    '|' => {}
+                    ' ' => grid[i][j] = false,
+                    _ => return Err(unexpected(lines, i, column)),
+                },
+                // Cells
+                (_, _) if c == '|' => return Err(unexpected(lines, i, column)),
+                (_, offset) => {
+                    let start = column - offset + 1;
+                    let excluded = (start..start + cell_width).all(|k| at(k) == '#');
+                    grid[i][j] = excluded;
+                }
+            }
+        }
+    }
+    Ok((grid, Vec::new()))
+}
+
+fn parse_raster(lines: &[Vec<char>]) -> Result<(Vec<Vec<bool>>, Vec<Crossing>), ParseError> {
+    let length = lines[0].len();
+    if length % 2 == 1 {
+        return Err(ParseError::InvalidSize);
+    }
+    let columns = length / 2;
+    if columns < 5 || columns % 2 != 1 || lines.len() < 5 || lines.len() % 2 != 1 {
+        return Err(ParseError::InvalidSize);
+    }
+
+    let mut grid = vec![vec![true; columns]; lines.len()];
+    let mut crossings = Vec::new();
+    for (i, (line, row)) in lines.iter().zip(grid.iter_mut()).enumerate() {
+        if line.len() > length {
+            return Err(unexpected(lines, i, length));
+        }
+        for (j, pixel) in row.iter_mut().enumerate() {
+            let pair = (
+                line.get(j * 2).copied().unwrap_or(' '),
+                line.get(j * 2 + 1).copied().unwrap_or(' '),
+            );
+            let column = if pair.0 == pair.1 { j * 2 } else { j * 2 + 1 };
+            let is_post = i % 2 == 0 && j % 2 == 0;
+            let is_cell = i % 2 == 1 && j % 2 == 1;
+            *pixel = match pair {
+                ('#', '#') => true,
+                _ if is_post => return Err(misplaced_post(i, j * 2)),
+                ('.', '.') => false,
+                ('=', '=') | ('|', '|') => {
+                    if is_cell {
+                        let over = if pair.0 == '=' {
+                            Axis::Horizontal
+                        } else {
+                            Axis::Vertical
+                        };
+                        crossings.push(Crossing {
+                            row: i / 2,
+                            col: j / 2,
+                            over,
+                        });
+                    }
+                    false
+                }
+                _ => return Err(unexpected(lines, i, column)),
+            };
+        }
+    }
+    Ok((grid, crossings))
+}
+
+// Build a maze from its raster, working out how the borders are joined from
+// the openings in the outer wall if `infer_topology` is set
+fn from_grid(grid: Vec<Vec<bool>>, crossings: Vec<Crossing>, infer_topology: bool) -> Maze {
+    let height = grid.len() / 2;
+    let width = grid[0].len() / 2;
+    if !infer_topology {
+        return Maze {
+            width,
+            height,
+            grid,
+            topology: Topology::PLANE,
+            crossings,
+        };
+    }
+    let horizontal = get_boundary(
+        &(0..height).map(|k| !grid[k * 2 + 1][0]).collect::<Vec<_>>(),
+        &(0..height)
+            .map(|k| !grid[k * 2 + 1][width * 2])
+            .collect::<Vec<_>>(),
+    );
+    let vertical = get_boundary(
+        &(0..width).map(|k| !grid[0][k * 2 + 1]).collect::<Vec<_>>(),
+        &(0..width)
+            .map(|k| !grid[height * 2][k * 2 + 1])
+            .collect::<Vec<_>>(),
+    );
+    Maze {
+        width,
+        height,
+        grid,
+        topology: Topology {
+            horizontal,
+            vertical,
+        },
+        crossings,
+    }
+}
+
+// How two opposite borders are joined given the openings along each of them.
+// Openings that both line up and mirror each other are taken to line up, and
+// openings that do neither leave the borders closed.
+fn get_boundary(near: &[bool], far: &[bool]) -> Boundary {
+    let has_openings = near.contains(&true) || far.contains(&true);
+    if has_openings && near == far {
+        Boundary::Wrapped
+    } else if has_openings && near.iter().eq(far.iter().rev()) {
+        Boundary::Twisted
+    } else {
+        Boundary::Closed
+    }
+}
+
+fn misplaced_post(line: usize, column: usize) -> ParseError {
+    ParseError::MisplacedPost {
+        line: line + 1,
+        column: column + 1,
+    }
+}
+
+fn unexpected(lines: &[Vec<char>], line: usize, column: usize) -> ParseError {
+    ParseError::UnexpectedCharacter {
+        line: line + 1,
+        column: column + 1,
+        found: lines[line].get(column).copied().unwrap_or(' '),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildOptions, WallWeights};
+
+    fn infer(text: &str) -> Maze {
+        let options = ParseOptions {
+            infer_topology: true,
+        };
+        Maze::from_ascii_with_options(text, options).unwrap()
+    }
+
+    #[test]
+    fn mazes_are_read_on_a_closed_plane() {
+        let text = "
+            +   +---+---+
+            | S     |   |
+            +   +---+   +
+            |         G |
+            +---+---+   +
+        ";
+        let maze = Maze::from_ascii(text).unwrap();
+        assert_eq!((maze.width, maze.height), (3, 2));
+        assert_eq!(maze.topology, Topology::PLANE);
+        assert!(!maze.grid[0][1]);
+        assert!(!maze.grid[4][5]);
+        assert!(maze.grid[0][3]);
+        assert!(!maze.grid[2][1]);
+        assert!(maze.grid[2][3]);
+
+        // Opposite corners mirror each other, like a twisted border
+        let twisted = infer(text);
+        assert_eq!(twisted.topology.vertical, Boundary::Twisted);
+        assert_eq!(twisted.grid, maze.grid);
+    }
+
+    #[test]
+    fn single_opening_leaves_the_borders_closed() {
+        let text = "
+            ##############
+            ....##......##
+            ##########..##
+            ##..........##
+            ##############
+        ";
+        let maze = infer(text);
+        assert_eq!(maze.topology, Topology::PLANE);
+        assert!(!maze.grid[1][0]);
+        assert_eq!(Maze::from_ascii(&maze.to_ascii()).unwrap().grid, maze.grid);
+    }
+
+    #[test]
+    fn unmatched_openings_only_close_their_own_borders() {
+        // The left and right openings line up, the top one has no match
+        let text = "
+            +---+   +---+
+                |       
+            +   +---+   +
+            |           |
+            +---+---+---+
+        ";
+        assert_eq!(Maze::from_ascii(text).unwrap().topology, Topology::PLANE);
+        let maze = infer(text);
+        assert_eq!(maze.topology, Topology::CYLINDER);
+        assert!(!maze.grid[0][3]);
+    }
+
+    #[test]
+    fn mirrored_openings_join_upside_down() {
+        let text = "
+            +---+---+---+
+                |       |
+            +---+---+   +
+            |       |   |
+            +   +---+   +
+            |       |
+            +---+---+---+
+        ";
+        let maze = infer(text);
+        assert_eq!(maze.topology, Topology::MOBIUS);
+        assert_eq!(infer(&maze.to_ascii()).topology, Topology::MOBIUS);
+    }
+
+    #[test]
+    fn labels_are_ignored_and_filled_cells_excluded() {
+        let text = "
+            o---o---o---o
+            |12  ###  7 |
+            o   o---o   o
+            | a       b |
+            o---o---o---o
+        ";
+        let maze = Maze::from_ascii(text).unwrap();
+        assert!(maze.grid[1][3]);
+        assert!(!maze.grid[1][1]);
+        assert!(!maze.grid[1][5]);
+        assert!(!maze.grid[3][3]);
+    }
+
+    #[test]
+    fn printed_mazes_are_read_back() {
+        for topology in [Topology::PLANE, Topology::TORUS, Topology::KLEIN] {
+            for seed in 0..10 {
+                let options = BuildOptions {
+                    topology,
+                    weave_density: 0.5,
+                    seed: Some(seed),
+                    ..Default::default()
+                };
+                let config = WallWeights::from_fn(|_| 1);
+                let maze = Maze::build_with_options(6, 5, config, options).unwrap();
+                let parsed = Maze::from_ascii(&maze.to_ascii()).unwrap();
+                assert_eq!(parsed.grid, maze.grid);
+                assert_eq!(parsed.crossings, maze.crossings);
+                assert_eq!(parsed.topology, Topology::PLANE);
+                let parsed = infer(&maze.to_classic(&Default::default()));
+                assert_eq!(parsed.grid, maze.grid);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        assert_eq!(Maze::from_ascii("  \n\n").err(), Some(ParseError::Empty));
+        assert_eq!(
+            Maze::from_ascii("+---+---+\n|   |   |\n+---+---+").err(),
+            Some(ParseError::InvalidSize)
+        );
+        let misplaced = "
+            +---+---+
+            |   +   |
+            +   +---+
+            |       |
+            +---+---+
+        ";
+        assert_eq!(
+            Maze::from_ascii(misplaced).err(),
+            Some(ParseError::MisplacedPost { line: 2, column: 5 })
+        );
+        let unexpected = "
+            +---+---+
+            |   |   |
+            +   +-x-+
+            |       |
+            +---+---+
+        ";
+        assert_eq!(
+            Maze::from_ascii(unexpected).err(),
+            Some(ParseError::UnexpectedCharacter {
+                line: 3,
+                column: 7,
+                found: 'x'
+            })
+        );
+    }
+}
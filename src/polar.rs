@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+use disjoint_sets::UnionFind;
+
+use crate::kruskal::{self, WallGraph};
+use crate::maze::{get_weight_of_neighbors, WallWeights};
+
+// A circular maze made of concentric rings around a single center cell.
+// Rings are split into more cells further out, so that cells keep roughly
+// the same size.
+#[derive(Debug)]
+pub struct PolarMaze {
+    // Wall state of every wall, `true` for a wall
+    walls: Vec<bool>,
+    layout: PolarLayout,
+}
+
+impl PolarMaze {
+    // Generate a maze with `rings` rings, including the center cell. `None` if
+    // the weights leave some cells out of reach.
+    pub fn build(rings: usize, config: PolarWallWeights) -> Option<PolarMaze> {
+        if rings < 2 {
+            return None;
+        }
+
+        let layout = PolarLayout::new(rings);
+        let number_of_walls = layout.walls.len();
+        let mut walls = vec![true; number_of_walls];
+        let mut cells = UnionFind::<usize>::new(layout.cell_count());
+
+        // The outer border is never removed
+        let candidates: Vec<bool> = layout.walls.iter().map(|w| w.cells.1.is_some()).collect();
+        let graph = PolarGrid {
+            layout: &layout,
+            config: &config,
+        };
+        kruskal::carve(
+            &graph,
+            &mut walls,
+            &mut cells,
+            &candidates,
+            &mut rand::thread_rng(),
+            &mut |_, _| {},
+        )?;
+
+        // Walls with a weight of 0 are never removed, which may leave cells
+        // out of reach
+        if (1..layout.cell_count()).any(|cell| !cells.equiv(0, cell)) {
+            return None;
+        }
+
+        Some(PolarMaze { walls, layout })
+    }
+
+    // Number of cells in each ring, starting with the center cell
+    pub fn ring_sizes(&self) -> &[usize] {
+        &self.layout.ring_sizes
+    }
+
+    // Cells reachable in one move from cell `index` of ring `ring`
+    pub fn neighbors(&self, ring: usize, index: usize) -> Vec<(usize, usize)> {
+        let Some(cell) = self.layout.cell_id(ring, index) else {
+            return Vec::new();
+        };
+        self.layout.cell_walls[cell]
+            .iter()
+            .filter(|&&id| !self.walls[id])
+            .filter_map(|&id| match self.layout.walls[id].cells {
+                (a, Some(b)) if a == cell => Some(b),
+                (a, Some(b)) if b == cell => Some(a),
+                _ => None,
+            })
+            .map(|other| self.layout.cell_coord(other))
+            .collect()
+    }
+
+    // Render the maze as an SVG image where each ring is `ring_width` wide
+    pub fn to_svg(&self, ring_width: f64) -> String {
+        let rings = self.layout.ring_sizes.len();
+        let margin = ring_width / 2.0;
+        let size = (rings as f64 * ring_width + margin) * 2.0;
+        let center = size / 2.0;
+        let divisions = self.layout.divisions() as f64;
+        let point = |(radius, angle): Post| {
+            let theta = angle as f64 / divisions * 2.0 * PI;
+            let r = radius as f64 * ring_width;
+            (center + r * theta.cos(), center + r * theta.sin())
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.1}" height="{:.1}">"#,
+            size, size
+        );
+        let _ = writeln!(
+            svg,
+            r#"<g stroke="black" stroke-width="{:.1}" stroke-linecap="round" fill="none">"#,
+            ring_width / 10.0
+        );
+        for (id, wall) in self.layout.walls.iter().enumerate() {
+            if !self.walls[id] {
+                continue;
+            }
+            let (x1, y1) = point(wall.posts.0);
+            let (x2, y2) = point(wall.posts.1);
+            match wall.kind {
+                WallKind::Spoke => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/>"#,
+                        x1, y1, x2, y2
+                    );
+                }
+                WallKind::Arc => {
+                    let radius = wall.posts.0 .0 as f64 * ring_width;
+                    let _ = writeln!(
+                        svg,
+                        r#"<path d="M {:.1} {:.1} A {:.1} {:.1} 0 0 1 {:.1} {:.1}"/>"#,
+                        x1, y1, radius, radius, x2, y2
+                    );
+                }
+            }
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+// ========== Polar Geometry ==========
+
+// A corner where walls meet, given as (radius, angle). Ring `r` lies between
+// radius `r` and `r + 1`, and angles are counted in steps of the outermost
+// ring's cells.
+type Post = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WallKind {
+    // Radial wall between two cells of the same ring
+    Spoke,
+    // Circular wall between a cell and the ring inside it
+    Arc,
+}
+
+#[derive(Debug)]
+struct PolarWall {
+    kind: WallKind,
+    // Inner then outer post for a spoke, clockwise order for an arc
+    posts: (Post, Post),
+    // Cells on either side of the wall. For a spoke, the first cell comes
+    // before the wall in clockwise order; for an arc, it is the inner cell.
+    // The outer border has no second cell.
+    cells: (usize, Option<usize>),
+}
+
+#[derive(Debug)]
+struct PolarLayout {
+    ring_sizes: Vec<usize>,
+    // ID of the first cell of each ring
+    ring_offsets: Vec<usize>,
+    walls: Vec<PolarWall>,
+    post_walls: HashMap<Post, Vec<usize>>,
+    // Walls around every cell
+    cell_walls: Vec<Vec<usize>>,
+}
+
+impl PolarLayout {
+    fn new(rings: usize) -> Self {
+        // Split a ring whenever its cells would become about twice as wide
+        // as they are tall
+        let mut ring_sizes = vec![1];
+        for r in 1..rings {
+            let previous = ring_sizes[r - 1];
+            let ratio = (2.0 * PI * r as f64 / previous as f64).round().max(1.0) as usize;
+            ring_sizes.push(previous * ratio);
+        }
+        let ring_offsets = ring_sizes
+            .iter()
+            .scan(0, |offset, &size| {
+                let start = *offset;
+                *offset += size;
+                Some(start)
+            })
+            .collect();
+
+        let cell_walls = vec![Vec::new(); ring_sizes.iter().sum()];
+        let mut layout = PolarLayout {
+            ring_sizes,
+            ring_offsets,
+            walls: Vec::new(),
+            post_walls: HashMap::new(),
+            cell_walls,
+        };
+
+        let divisions = layout.divisions();
+        for r in 1..rings {
+            let size = layout.ring_sizes[r];
+            let step = divisions / size;
+            let ratio = size / layout.ring_sizes[r - 1];
+            for i in 0..size {
+                let cell = layout.ring_offsets[r] + i;
+                let start = i * step;
+                let end = ((i + 1) * step) % divisions;
+
+                let next = layout.ring_offsets[r] + (i + 1) % size;
+                layout.add_wall(
+                    WallKind::Spoke,
+                    ((r, end), (r + 1, end)),
+                    (cell, Some(next)),
+                );
+
+                let parent = layout.ring_offsets[r - 1] + i / ratio;
+                layout.add_wall(WallKind::Arc, ((r, start), (r, end)), (parent, Some(cell)));
+            }
+        }
+
+        // Outer border
+        let outer = rings - 1;
+        let size = layout.ring_sizes[outer];
+        let step = divisions / size;
+        for i in 0..size {
+            let cell = layout.ring_offsets[outer] + i;
+            let posts = ((rings, i * step), (rings, ((i + 1) * step) % divisions));
+            layout.add_wall(WallKind::Arc, posts, (cell, None));
+        }
+
+        layout
+    }
+
+    fn add_wall(&mut self, kind: WallKind, posts: (Post, Post), cells: (usize, Option<usize>)) {
+        let id = self.walls.len();
+        self.walls.push(PolarWall { kind, posts, cells });
+        self.post_walls.entry(posts.0).or_default().push(id);
+        self.post_walls.entry(posts.1).or_default().push(id);
+        self.cell_walls[cells.0].push(id);
+        if let Some(cell) = cells.1 {
+            self.cell_walls[cell].push(id);
+        }
+    }
+
+    // Number of angle steps around the circle
+    fn divisions(&self) -> usize {
+        self.ring_sizes.last().copied().unwrap_or(1)
+    }
+
+    fn cell_count(&self) -> usize {
+        self.ring_sizes.iter().sum()
+    }
+
+    fn cell_id(&self, ring: usize, index: usize) -> Option<usize> {
+        if ring < self.ring_sizes.len() && index < self.ring_sizes[ring] {
+            Some(self.ring_offsets[ring] + index)
+        } else {
+            None
+        }
+    }
+
+    fn cell_coord(&self, id: usize) -> (usize, usize) {
+        let ring = self.ring_offsets.partition_point(|&offset| offset <= id) - 1;
+        (ring, id - self.ring_offsets[ring])
+    }
+
+    // The walls meeting the given wall at one of its posts, ordered like the
+    // `WallWeights` diagram: the wall on the side of the first cell, the
+    // wall continuing straight on, and the wall on the side of the second
+    // cell. Walls that do not exist at the post count as absent, and a post
+    // on the outer border has no neighbors.
+    fn post_neighbors(&self, id: usize, post: Post) -> Option<[Option<usize>; 3]> {
+        if post.0 >= self.ring_sizes.len() {
+            return None;
+        }
+        let wall = &self.walls[id];
+        let others: Vec<usize> = self.post_walls[&post]
+            .iter()
+            .copied()
+            .filter(|&other| other != id)
+            .collect();
+        let find = |kind: WallKind, test: &dyn Fn(&PolarWall) -> bool| {
+            others
+                .iter()
+                .copied()
+                .find(|&other| self.walls[other].kind == kind && test(&self.walls[other]))
+        };
+
+        Some(match wall.kind {
+            WallKind::Spoke => [
+                find(WallKind::Arc, &|arc| arc.posts.1 == post),
+                find(WallKind::Spoke, &|_| true),
+                find(WallKind::Arc, &|arc| arc.posts.0 == post),
+            ],
+            WallKind::Arc => [
+                find(WallKind::Spoke, &|spoke| spoke.posts.1 == post),
+                find(WallKind::Arc, &|_| true),
+                find(WallKind::Spoke, &|spoke| spoke.posts.0 == post),
+            ],
+        })
+    }
+}
+
+struct PolarGrid<'a> {
+    layout: &'a PolarLayout,
+    config: &'a PolarWallWeights,
+}
+
+impl WallGraph for PolarGrid<'_> {
+    fn edge_count(&self) -> usize {
+        self.layout.walls.len()
+    }
+
+    fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)> {
+        match self.layout.walls[id].cells {
+            (a, Some(b)) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    fn neighbor_edges(&self, id: usize) -> Vec<usize> {
+        let (first, second) = self.layout.walls[id].posts;
+        [first, second]
+            .iter()
+            .flat_map(|post| &self.layout.post_walls[post])
+            .copied()
+            .filter(|&other| other != id)
+            .collect()
+    }
+
+    fn weight(&self, edges: &[bool], id: usize) -> Option<u32> {
+        let wall = &self.layout.walls[id];
+        let mut neighbors = Vec::with_capacity(6);
+        for post in [wall.posts.0, wall.posts.1] {
+            if let Some(group) = self.layout.post_neighbors(id, post) {
+                neighbors.extend(group.map(|other| other.is_some_and(|other| edges[other])));
+            }
+        }
+        let config = match wall.kind {
+            WallKind::Spoke => &self.config.spoke,
+            WallKind::Arc => &self.config.arc,
+        };
+        get_weight_of_neighbors(config, &neighbors)
+    }
+}
+
+// ========== Polar Wall Weights ==========
+
+// Spokes and arcs are classified like the walls of a square maze, looking at
+// the walls meeting them at both ends. Where no wall can exist, such as
+// inside the center cell or between two cells of an inner ring that is not
+// split, the wall counts as absent.
+pub struct PolarWallWeights {
+    // Weights of radial walls between two cells of the same ring
+    pub spoke: WallWeights,
+    // Weights of circular walls between two rings
+    pub arc: WallWeights,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn build(rings: usize) -> PolarMaze {
+        let config = PolarWallWeights {
+            spoke: WallWeights::from_fn(|_| 1),
+            arc: WallWeights::from_fn(|_| 1),
+        };
+        PolarMaze::build(rings, config).unwrap()
+    }
+
+    #[test]
+    fn rings_are_split_as_they_widen() {
+        let maze = build(12);
+        assert_eq!(
+            maze.ring_sizes(),
+            [1, 6, 12, 24, 24, 24, 48, 48, 48, 48, 48, 48]
+        );
+        for r in 1..maze.ring_sizes().len() {
+            let previous = maze.ring_sizes()[r - 1];
+            let ratio = (2.0 * PI * r as f64 / previous as f64).round() as usize;
+            assert_eq!(maze.ring_sizes()[r], previous * ratio.max(1));
+        }
+    }
+
+    #[test]
+    fn mazes_are_spanning_trees() {
+        for rings in [2, 5, 9] {
+            let maze = build(rings);
+            let cells: Vec<(usize, usize)> = (0..rings)
+                .flat_map(|ring| (0..maze.ring_sizes()[ring]).map(move |index| (ring, index)))
+                .collect();
+
+            let mut reached = HashSet::from([(0, 0)]);
+            let mut stack = vec![(0, 0)];
+            while let Some((ring, index)) = stack.pop() {
+                for next in maze.neighbors(ring, index) {
+                    if reached.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+            assert_eq!(reached.len(), cells.len());
+
+            let openings: usize = cells
+                .iter()
+                .map(|&(ring, index)| maze.neighbors(ring, index).len())
+                .sum::<usize>()
+                / 2;
+            assert_eq!(openings, cells.len() - 1);
+        }
+    }
+
+    #[test]
+    fn svg_draws_every_remaining_wall_once() {
+        let maze = build(6);
+        let svg = maze.to_svg(10.0);
+        let drawn = svg.matches("<line").count() + svg.matches("<path").count();
+        assert_eq!(drawn, maze.walls.iter().filter(|&&wall| wall).count());
+    }
+}
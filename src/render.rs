@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use crate::maze::{Axis, Maze};
+
+// Extra information drawn inside the cells of a rendered maze. When several
+// apply to the same cell, the start and goal come first, then the distance,
+// then the path.
+#[derive(Debug, Clone, Default)]
+pub struct Overlay {
+    pub start: Option<(usize, usize)>,
+    pub goal: Option<(usize, usize)>,
+    // Number shown in every cell, indexed by `row * width + col`, such as
+    // the distance from the start
+    pub distances: Option<Vec<Option<u32>>>,
+    // Cells to mark as part of a route
+    pub path: Vec<(usize, usize)>,
+}
+
+// What to draw inside a cell
+enum Mark {
+    Start,
+    Goal,
+    Distance(u32),
+    Path,
+    Crossing(Axis),
+    Excluded,
+    Empty,
+}
+
+// Characters used by the renderers with three columns per cell
+struct Glyphs {
+    // Post given whether there is a wall above, below, left and right of it
+    post: fn(bool, bool, bool, bool) -> char,
+    horizontal: char,
+    vertical: char,
+    path: &'static str,
+    // Crossings with the passage on top running horizontally and vertically
+    crossings: [&'static str; 2],
+    excluded: &'static str,
+}
+
+const BOX_DRAWING: Glyphs = Glyphs {
+    post: |up, down, left, right| match (up, down, left, right) {
+        (false, false, false, false) => ' ',
+        (true, false, false, false) => '╵',
+        (false, true, false, false) => '╷',
+        (false, false, true, false) => '╴',
+        (false, false, false, true) => '╶',
+        (true, true, false, false) => '│',
+        (false, false, true, true) => '─',
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    },
+    horizontal: '─',
+    vertical: '│',
+    path: " • ",
+    crossings: [" ═ ", " ║ "],
+    excluded: "███",
+};
+
+const CLASSIC: Glyphs = Glyphs {
+    post: |_, _, _, _| '+',
+    horizontal: '-',
+    vertical: '|',
+    path: " * ",
+    crossings: [" = ", " H "],
+    excluded: "###",
+};
+
+impl Maze {
+    // Draw the maze with Unicode box-drawing characters, three columns per
+    // cell
+    pub fn to_box_drawing(&self, overlay: &Overlay) -> String {
+        self.render_wide(overlay, &BOX_DRAWING)
+    }
+
+    // Draw the maze in the `+---+` text layout used by micromouse contests,
+    // three columns per cell
+    pub fn to_classic(&self, overlay: &Overlay) -> String {
+        self.render_wide(overlay, &CLASSIC)
+    }
+
+    // Draw the maze with half-block characters, one column per wall or cell
+    // and one line per row of cells. Each line shows the walls above a row
+    // of cells in the upper halves and the cells in the lower halves. A mark
+    // takes the whole character, hiding the wall above its cell. Distances
+    // are shown as a single base-36 digit.
+    pub fn to_half_blocks(&self, overlay: &Overlay) -> String {
+        let path: HashSet<(usize, usize)> = overlay.path.iter().copied().collect();
+        let mut res = String::new();
+        for i in (0..self.grid.len()).step_by(2) {
+            for (j, &top) in self.grid[i].iter().enumerate() {
+                let bottom = self.grid.get(i + 1).map(|row| row[j]);
+                let mark = (j % 2 == 1 && bottom == Some(false))
+                    .then(|| self.get_mark(overlay, &path, (i / 2, j / 2)));
+                res.push(match (top, bottom, mark) {
+                    (_, _, Some(Mark::Start)) => 'S',
+                    (_, _, Some(Mark::Goal)) => 'G',
+                    (_, _, Some(Mark::Distance(distance))) => {
+                        char::from_digit(distance, 36).unwrap_or('+')
+                    }
+                    (_, _, Some(Mark::Path)) => '•',
+                    (true, Some(true), _) => '█',
+                    (true, _, _) => '▀',
+                    (false, Some(true), _) => '▄',
+                    (false, _, _) => ' ',
+                });
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    // Draw the maze with three columns per cell
+    fn render_wide(&self, overlay: &Overlay, glyphs: &Glyphs) -> String {
+        let path: HashSet<(usize, usize)> = overlay.path.iter().copied().collect();
+        let wall = |i: usize, j: usize| {
+            self.grid
+                .get(i)
+                .and_then(|row| row.get(j))
+                .copied()
+                .unwrap_or(false)
+        };
+
+        let mut res = String::new();
+        for (i, row) in self.grid.iter().enumerate() {
+            for (j, &is_wall) in row.iter().enumerate() {
+                match (i % 2, j % 2) {
+                    (0, 0) => res.push((glyphs.post)(
+                        i > 0 && wall(i - 1, j),
+                        wall(i + 1, j),
+                        j > 0 && wall(i, j - 1),
+                        wall(i, j + 1),
+                    )),
+                    (0, _) => {
+                        let c = if is_wall { glyphs.horizontal } else { ' ' };
+                        res.extend([c; 3]);
+                    }
+                    (_, 0) => res.push(if is_wall { glyphs.vertical } else { ' ' }),
+                    _ => {
+                        let mark = self.get_mark(overlay, &path, (i / 2, j / 2));
+                        match mark {
+                            Mark::Start => res.push_str(" S "),
+                            Mark::Goal => res.push_str(" G "),
+                            Mark::Distance(distance) if distance < 1000 => {
+                                res.push_str(&format!("{:>3}", distance))
+                            }
+                            Mark::Distance(_) => res.push_str("+++"),
+                            Mark::Path => res.push_str(glyphs.path),
+                            Mark::Crossing(Axis::Horizontal) => res.push_str(glyphs.crossings[0]),
+                            Mark::Crossing(Axis::Vertical) => res.push_str(glyphs.crossings[1]),
+                            Mark::Excluded => res.push_str(glyphs.excluded),
+                            Mark::Empty => res.push_str("   "),
+                        }
+                    }
+                }
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    fn get_mark(
+        &self,
+        overlay: &Overlay,
+        path: &HashSet<(usize, usize)>,
+        cell: (usize, usize),
+    ) -> Mark {
+        let (row, col) = cell;
+        if self.grid[row * 2 + 1][col * 2 + 1] {
+            return Mark::Excluded;
+        }
+        let distance = overlay
+            .distances
+            .as_ref()
+            .and_then(|distances| distances.get(row * self.width + col).copied().flatten());
+        if overlay.start == Some(cell) {
+            Mark::Start
+        } else if overlay.goal == Some(cell) {
+            Mark::Goal
+        } else if let Some(distance) = distance {
+            Mark::Distance(distance)
+        } else if path.contains(&cell) {
+            Mark::Path
+        } else if let Some(crossing) = self.crossings.iter().find(|c| (c.row, c.col) == cell) {
+            Mark::Crossing(crossing.over)
+        } else {
+            Mark::Empty
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{BuildOptions, WallWeights};
+
+    fn build(width: usize, height: usize) -> Maze {
+        let options = BuildOptions {
+            seed: Some(1),
+            ..Default::default()
+        };
+        Maze::build_with_options(width, height, WallWeights::from_fn(|_| 1), options).unwrap()
+    }
+
+    #[test]
+    fn half_blocks_show_marks_below_walls() {
+        let maze = build(4, 3);
+        let overlay = Overlay {
+            start: Some((0, 0)),
+            goal: Some((2, 3)),
+            ..Default::default()
+        };
+        let text = maze.to_half_blocks(&overlay);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].chars().nth(1), Some('S'));
+        assert_eq!(lines[2].chars().nth(7), Some('G'));
+    }
+
+    #[test]
+    fn half_blocks_show_every_path_cell() {
+        let maze = build(5, 4);
+        let path: Vec<(usize, usize)> = (0..4)
+            .flat_map(|row| (0..5).map(move |col| (row, col)))
+            .collect();
+        let overlay = Overlay {
+            path,
+            ..Default::default()
+        };
+        let text = maze.to_half_blocks(&overlay);
+        assert_eq!(text.matches('•').count(), 20);
+    }
+}
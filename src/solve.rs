@@ -0,0 +1,241 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::maze::{Boundary, Direction, Maze};
+
+// A path found through a maze
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    // Cells from the start to the goal, both included
+    pub path: Vec<(usize, usize)>,
+    // Total cost of the moves along the path
+    pub cost: u32,
+    // Number of search states expanded before reaching the goal
+    pub explored: usize,
+}
+
+// A single move considered by a search, passed to cost functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub direction: Direction,
+    // Direction of the move that led to `from`, `None` at the start
+    pub previous: Option<Direction>,
+    // Number of cells travelled, 2 when passing under a crossing
+    pub length: u32,
+}
+
+// Cost of every move being the number of cells it travels
+pub fn distance(step: &Step) -> u32 {
+    step.length
+}
+
+// Estimate of the remaining cost used by A*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    // No estimate, which makes A* behave like Dijkstra
+    Zero,
+    Manhattan,
+    // Rounded down so that it never overestimates
+    Euclidean,
+}
+
+impl Heuristic {
+    // Distance between two cells ignoring walls, taking the shortest way
+    // across any joined border. Never larger than the cost of the path
+    // between them when every move costs at least its `length`.
+    pub fn estimate(self, maze: &Maze, from: (usize, usize), to: (usize, usize)) -> u32 {
+        if self == Heuristic::Zero {
+            return 0;
+        }
+        let (width, height) = (maze.width, maze.height);
+        let topology = maze.topology;
+        let mut best = u32::MAX;
+        for across_horizontal in [false, true] {
+            if across_horizontal && topology.horizontal == Boundary::Closed {
+                continue;
+            }
+            for across_vertical in [false, true] {
+                if across_vertical && topology.vertical == Boundary::Closed {
+                    continue;
+                }
+                let (mut row, mut col) = from;
+                if across_horizontal && topology.horizontal == Boundary::Twisted {
+                    row = height - 1 - row;
+                }
+                if across_vertical && topology.vertical == Boundary::Twisted {
+                    col = width - 1 - col;
+                }
+                let mut d_row = row.abs_diff(to.0);
+                let mut d_col = col.abs_diff(to.1);
+                if across_horizontal {
+                    d_col = width - d_col;
+                }
+                if across_vertical {
+                    d_row = height - d_row;
+                }
+                let estimate = match self {
+                    Heuristic::Manhattan => d_row + d_col,
+                    _ => ((d_row * d_row + d_col * d_col) as f64).sqrt().floor() as usize,
+                };
+                best = best.min(estimate as u32);
+            }
+        }
+        best
+    }
+}
+
+// Shortest path by number of moves
+pub fn bfs(maze: &Maze, start: (usize, usize), goal: (usize, usize)) -> Option<Solution> {
+    if !is_open(maze, start) || !is_open(maze, goal) {
+        return None;
+    }
+    let cell_count = maze.width * maze.height;
+    let mut parents = vec![None; cell_count];
+    let mut visited = vec![false; cell_count];
+    let mut queue = VecDeque::new();
+    let mut explored = 0;
+    visited[get_cell_index(maze, start)] = true;
+    queue.push_back(start);
+
+    while let Some(cell) = queue.pop_front() {
+        explored += 1;
+        if cell == goal {
+            let mut path = vec![cell];
+            while let Some(parent) = parents[get_cell_index(maze, *path.last()?)] {
+                path.push(parent);
+            }
+            path.reverse();
+            return Some(Solution {
+                cost: (path.len() - 1) as u32,
+                path,
+                explored,
+            });
+        }
+        for next in maze.neighbors(cell.0, cell.1) {
+            let index = get_cell_index(maze, next);
+            if !visited[index] {
+                visited[index] = true;
+                parents[index] = Some(cell);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// Cheapest path where every move costs `cost(step)`
+pub fn dijkstra<F>(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    cost: F,
+) -> Option<Solution>
+where
+    F: Fn(&Step) -> u32,
+{
+    astar(maze, start, goal, Heuristic::Zero, cost)
+}
+
+// Cheapest path where every move costs `cost(step)`, guided by `heuristic`.
+// The path is only guaranteed to be the cheapest when the heuristic never
+// overestimates, which holds for the built-in ones as long as every move
+// costs at least its `length`.
+pub fn astar<F>(
+    maze: &Maze,
+    start: (usize, usize),
+    goal: (usize, usize),
+    heuristic: Heuristic,
+    cost: F,
+) -> Option<Solution>
+where
+    F: Fn(&Step) -> u32,
+{
+    if !is_open(maze, start) || !is_open(maze, goal) {
+        return None;
+    }
+
+    // A search state is a cell together with the direction it was entered
+    // in, so that costs may depend on the previous move
+    let state_count = maze.width * maze.height * STATES_PER_CELL;
+    let mut costs = vec![u32::MAX; state_count];
+    let mut parents: Vec<Option<usize>> = vec![None; state_count];
+    let mut done = vec![false; state_count];
+    let mut queue = BinaryHeap::new();
+    let mut explored = 0;
+
+    let first = get_state_index(maze, start, None);
+    costs[first] = 0;
+    queue.push(Reverse((heuristic.estimate(maze, start, goal), 0, first)));
+
+    while let Some(Reverse((_, current_cost, state))) = queue.pop() {
+        if done[state] {
+            continue;
+        }
+        done[state] = true;
+        explored += 1;
+
+        let (cell, previous) = get_state(maze, state);
+        if cell == goal {
+            let mut path = vec![cell];
+            let mut state = state;
+            while let Some(parent) = parents[state] {
+                path.push(get_state(maze, parent).0);
+                state = parent;
+            }
+            path.reverse();
+            return Some(Solution {
+                path,
+                cost: current_cost,
+                explored,
+            });
+        }
+
+        for m in maze.moves(cell.0, cell.1) {
+            let step = Step {
+                from: cell,
+                to: m.to,
+                direction: m.direction,
+                previous,
+                length: m.length,
+            };
+            let next = get_state_index(maze, m.to, Some(m.direction));
+            let next_cost = current_cost.saturating_add(cost(&step));
+            if !done[next] && next_cost < costs[next] {
+                costs[next] = next_cost;
+                parents[next] = Some(state);
+                let priority = next_cost.saturating_add(heuristic.estimate(maze, m.to, goal));
+                queue.push(Reverse((priority, next_cost, next)));
+            }
+        }
+    }
+    None
+}
+
+// ========== Search States ==========
+
+// One state per direction a cell may be entered in, plus one for the start
+const STATES_PER_CELL: usize = 5;
+
+pub(crate) fn is_open(maze: &Maze, (row, col): (usize, usize)) -> bool {
+    row < maze.height && col < maze.width && !maze.grid[row * 2 + 1][col * 2 + 1]
+}
+
+fn get_cell_index(maze: &Maze, (row, col): (usize, usize)) -> usize {
+    row * maze.width + col
+}
+
+fn get_state_index(maze: &Maze, cell: (usize, usize), entered: Option<Direction>) -> usize {
+    let direction = match entered {
+        Some(direction) => direction as usize,
+        None => STATES_PER_CELL - 1,
+    };
+    get_cell_index(maze, cell) * STATES_PER_CELL + direction
+}
+
+fn get_state(maze: &Maze, index: usize) -> ((usize, usize), Option<Direction>) {
+    let cell = index / STATES_PER_CELL;
+    let direction = Direction::ALL.get(index % STATES_PER_CELL).copied();
+    ((cell / maze.width, cell % maze.width), direction)
+}
@@ -0,0 +1,296 @@
+use std::thread;
+
+use disjoint_sets::UnionFind;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::kruskal;
+use crate::mask::CellMask;
+use crate::maze::{
+    build_grid, get_adjacent_cells, get_constrained_edge_id, get_edge_count, BuildError,
+    BuildOptions, Edge, Maze, SquareGrid, Topology, WallWeights,
+};
+
+// Settings for `Maze::build_tiled`
+#[derive(Debug, Clone)]
+pub struct TileOptions {
+    // Smallest width and height of a tile in cells. Tiles are stretched to
+    // cover the maze evenly, so they are at most twice as large.
+    pub tile_size: usize,
+    // Threads generating tiles, 0 for one per core
+    pub threads: usize,
+    // Seed of the random generator, as in `BuildOptions`. The maze does not
+    // depend on the number of threads.
+    pub seed: Option<u64>,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        TileOptions {
+            tile_size: 256,
+            threads: 0,
+            seed: None,
+        }
+    }
+}
+
+// Rows and columns of cells on either side of a border between tiles whose
+// walls are drawn again once the tiles are joined
+const BORDER_DEPTH: usize = 2;
+
+// A rectangle of cells generated as a maze of its own
+struct Tile {
+    row: usize,
+    col: usize,
+    width: usize,
+    height: usize,
+    seed: u64,
+}
+
+impl Maze {
+    // Generate a large maze in parallel by splitting it into tiles and
+    // generating every tile as with `build`. The walls between tiles, and
+    // the walls of the tiles within two cells of them, are then removed as
+    // with `build` in a single pass over the joined maze, weighted by their
+    // real neighbors. The result is a tree. Walls further inside a tile were
+    // still weighted without knowing the tiles around it, so the walls
+    // between tiles stay slightly more often closed than with `build`.
+    pub fn build_tiled(
+        width: usize,
+        height: usize,
+        config: WallWeights,
+        options: &TileOptions,
+    ) -> Result<Maze, BuildError> {
+        if width < 2 || height < 2 || options.tile_size < 2 {
+            return Err(BuildError::InvalidSize);
+        }
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let row_bounds = get_tile_bounds(height, options.tile_size);
+        let col_bounds = get_tile_bounds(width, options.tile_size);
+        let mut tiles = Vec::new();
+        for rows in row_bounds.windows(2) {
+            for cols in col_bounds.windows(2) {
+                tiles.push(Tile {
+                    row: rows[0],
+                    col: cols[0],
+                    width: cols[1] - cols[0],
+                    height: rows[1] - rows[0],
+                    seed: rng.gen(),
+                });
+            }
+        }
+
+        // Generate the tiles, each thread taking a contiguous share
+        let threads = match options.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let share = tiles.len().div_ceil(threads);
+        let tile_mazes: Vec<Result<Maze, BuildError>> = thread::scope(|scope| {
+            let handles: Vec<_> = tiles
+                .chunks(share)
+                .map(|tiles| {
+                    let config = &config;
+                    scope.spawn(move || {
+                        tiles
+                            .iter()
+                            .map(|tile| {
+                                let options = BuildOptions {
+                                    seed: Some(tile.seed),
+                                    ..Default::default()
+                                };
+                                Maze::build_with_options(
+                                    tile.width,
+                                    tile.height,
+                                    config.clone(),
+                                    options,
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("tile generation panicked"))
+                .collect()
+        });
+
+        // Copy the walls of the tiles into place. Walls between two tiles
+        // stay closed for now.
+        let topology = Topology::PLANE;
+        let mut edges = vec![true; get_edge_count(width, height, topology)];
+        for (tile, tile_maze) in tiles.iter().zip(tile_mazes) {
+            let tile_maze = tile_maze?;
+            for edge in tile_maze.edges() {
+                let placed = match edge {
+                    Edge::East(row, col) => Edge::East(tile.row + row, tile.col + col),
+                    Edge::South(row, col) => Edge::South(tile.row + row, tile.col + col),
+                };
+                let id = get_constrained_edge_id(width, height, topology, placed)
+                    .ok_or(BuildError::InvalidSize)?;
+                edges[id] = tile_maze.wall(edge) == Some(true);
+            }
+        }
+
+        // Every tile saw its borders as the border of the maze, so the walls
+        // near a border are drawn again together with the walls between
+        // tiles, now weighted by their real neighbors
+        let near_rows = get_near_border(&row_bounds);
+        let near_cols = get_near_border(&col_bounds);
+        let mut candidates = vec![false; edges.len()];
+        let mut cells = UnionFind::<usize>::new(width * height);
+        for (id, candidate) in candidates.iter_mut().enumerate() {
+            let (cell_a, cell_b) =
+                get_adjacent_cells(width, height, topology, id).ok_or(BuildError::InvalidSize)?;
+            *candidate = [cell_a, cell_b]
+                .iter()
+                .any(|&cell| near_rows[cell / width] || near_cols[cell % width]);
+            if *candidate {
+                edges[id] = true;
+            } else if !edges[id] {
+                cells.union(cell_a, cell_b);
+            }
+        }
+
+        let graph = SquareGrid {
+            width,
+            height,
+            topology,
+            config: &config,
+        };
+        kruskal::carve(
+            &graph,
+            &mut edges,
+            &mut cells,
+            &candidates,
+            &mut rng,
+            &mut |_, _| {},
+        )
+        .ok_or(BuildError::InvalidSize)?;
+
+        let mask = CellMask::new(width, height);
+        Ok(Maze {
+            width,
+            height,
+            grid: build_grid(width, height, topology, &edges, &mask)
+                .ok_or(BuildError::InvalidSize)?,
+            topology,
+            crossings: Vec::new(),
+        })
+    }
+}
+
+// Start of every tile along one side of the maze, followed by the end of the
+// last tile
+fn get_tile_bounds(length: usize, tile_size: usize) -> Vec<usize> {
+    let count = (length / tile_size).max(1);
+    (0..=count).map(|i| i * length / count).collect()
+}
+
+// Whether every row or column lies within `BORDER_DEPTH` cells of another
+// tile, given the tile bounds
+fn get_near_border(bounds: &[usize]) -> Vec<bool> {
+    let length = bounds[bounds.len() - 1];
+    let mut near = vec![false; length];
+    for &bound in &bounds[1..bounds.len() - 1] {
+        let start = bound.saturating_sub(BORDER_DEPTH);
+        let end = (bound + BORDER_DEPTH).min(length);
+        near[start..end].fill(true);
+    }
+    near
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::WallType;
+
+    fn options(tile_size: usize, threads: usize, seed: u64) -> TileOptions {
+        TileOptions {
+            tile_size,
+            threads,
+            seed: Some(seed),
+        }
+    }
+
+    // Weights favoring some wall types over others, so that a bias along the
+    // tile borders shows
+    fn ramp() -> WallWeights {
+        WallWeights::from_fn(|wall_type| {
+            let index = WallType::iter().position(|t| t == wall_type).unwrap();
+            1 + 20 * (index as u32 % 5)
+        })
+    }
+
+    // Openings on the borders between tiles of `tile_size` cells, and dead
+    // ends
+    fn count_openings_and_dead_ends(maze: &Maze, tile_size: usize) -> (usize, usize) {
+        let openings = maze
+            .edges()
+            .filter(|&edge| match edge {
+                Edge::East(_, col) => (col + 1) % tile_size == 0,
+                Edge::South(row, _) => (row + 1) % tile_size == 0,
+            })
+            .filter(|&edge| maze.wall(edge) == Some(false))
+            .count();
+        let dead_ends = (0..maze.height)
+            .flat_map(|row| (0..maze.width).map(move |col| (row, col)))
+            .filter(|&(row, col)| maze.neighbors(row, col).len() == 1)
+            .count();
+        (openings, dead_ends)
+    }
+
+    #[test]
+    fn tiled_mazes_are_spanning_trees() {
+        for (width, height, tile_size) in [(2, 2, 2), (9, 7, 2), (20, 13, 4), (30, 30, 64)] {
+            for seed in 0..5 {
+                let maze =
+                    Maze::build_tiled(width, height, ramp(), &options(tile_size, 2, seed)).unwrap();
+                let openings = maze
+                    .edges()
+                    .filter(|&edge| maze.wall(edge) == Some(false))
+                    .count();
+                assert_eq!(openings, width * height - 1);
+                assert!(maze.is_connected());
+            }
+        }
+    }
+
+    #[test]
+    fn mazes_do_not_depend_on_the_thread_count() {
+        let single = Maze::build_tiled(40, 30, ramp(), &options(8, 1, 3)).unwrap();
+        let parallel = Maze::build_tiled(40, 30, ramp(), &options(8, 4, 3)).unwrap();
+        assert_eq!(single.grid, parallel.grid);
+    }
+
+    #[test]
+    fn tile_borders_open_about_as_often_as_with_build() {
+        let (mut built, mut tiled) = ((0, 0), (0, 0));
+        for seed in 0..50 {
+            let build_options = BuildOptions {
+                seed: Some(seed),
+                ..Default::default()
+            };
+            let maze = Maze::build_with_options(32, 32, ramp(), build_options).unwrap();
+            let (openings, dead_ends) = count_openings_and_dead_ends(&maze, 8);
+            built = (built.0 + openings, built.1 + dead_ends);
+            let maze =
+                Maze::build_tiled(32, 32, ramp(), &super::tests::options(8, 1, seed)).unwrap();
+            let (openings, dead_ends) = count_openings_and_dead_ends(&maze, 8);
+            tiled = (tiled.0 + openings, tiled.1 + dead_ends);
+        }
+
+        let ratio = |tiled: usize, built: usize| tiled as f64 / built as f64;
+        assert!(ratio(tiled.0, built.0) > 0.8, "{:?} {:?}", tiled, built);
+        assert!(
+            (ratio(tiled.1, built.1) - 1.0).abs() < 0.05,
+            "{:?} {:?}",
+            tiled,
+            built
+        );
+    }
+}
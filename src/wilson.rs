@@ -0,0 +1,177 @@
+use std::collections::{HashMap, HashSet};
+
+use disjoint_sets::UnionFind;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+
+use crate::kruskal::{CarveEvent, WallGraph};
+
+// Remove walls along loop-erased random walks (Wilson's algorithm), which
+// picks every spanning tree of the candidate edges with the same
+// probability. Cells already joined in `cells` are treated as a single
+// node, so the tree is uniform among the trees containing the edges removed
+// beforehand. Arguments are the same as for `kruskal::carve`, and only
+// removed edges are reported. Returns `None` if the candidate edges cannot
+// join every cell they touch.
+pub(crate) fn carve<G: WallGraph>(
+    graph: &G,
+    edges: &mut [bool],
+    cells: &mut UnionFind<usize>,
+    candidates: &[bool],
+    rng: &mut dyn RngCore,
+    on_event: &mut dyn FnMut(&[bool], CarveEvent),
+) -> Option<()> {
+    // Edges leaving every node, where an edge joining a node to itself can
+    // never be part of the tree
+    let mut adjacent: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+    let mut components = cells.clone();
+    for (id, _) in candidates.iter().enumerate().filter(|(_, &c)| c) {
+        let (cell_a, cell_b) = graph.adjacent_cells(id)?;
+        let (a, b) = (cells.find(cell_a), cells.find(cell_b));
+        if a == b {
+            continue;
+        }
+        adjacent.entry(a).or_default().push((id, b));
+        adjacent.entry(b).or_default().push((id, a));
+        components.union(a, b);
+    }
+
+    // A walk only ends once it reaches the tree, so every node must be
+    // reachable from the first one
+    let mut nodes: Vec<usize> = adjacent.keys().copied().collect();
+    nodes.sort_unstable();
+    let Some(&first) = nodes.first() else {
+        return Some(());
+    };
+    if nodes.iter().any(|&node| !components.equiv(node, first)) {
+        return None;
+    }
+
+    let mut in_tree = HashSet::from([first]);
+    let mut next = HashMap::new();
+    for &start in &nodes {
+        // Walk until reaching the tree, keeping only the last exit from every
+        // node, which erases any loop
+        let mut node = start;
+        while !in_tree.contains(&node) {
+            let &(id, to) = adjacent[&node].choose(rng)?;
+            next.insert(node, (id, to));
+            node = to;
+        }
+
+        // Add the loop-erased path to the tree
+        let mut node = start;
+        while in_tree.insert(node) {
+            let (id, to) = next[&node];
+            let (cell_a, cell_b) = graph.adjacent_cells(id)?;
+            cells.union(cell_a, cell_b);
+            edges[id] = false;
+            on_event(edges, CarveEvent::Removed { id });
+            node = to;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::mask::CellMask;
+    use crate::maze::{Algorithm, BuildOptions, Maze, Topology, WallWeights};
+
+    // A row of cells, with edge `i` between cells `i` and `i + 1`
+    struct Row(usize);
+
+    impl WallGraph for Row {
+        fn edge_count(&self) -> usize {
+            self.0 - 1
+        }
+
+        fn adjacent_cells(&self, id: usize) -> Option<(usize, usize)> {
+            (id + 1 < self.0).then_some((id, id + 1))
+        }
+
+        fn neighbor_edges(&self, _id: usize) -> Vec<usize> {
+            Vec::new()
+        }
+
+        fn weight(&self, _edges: &[bool], _id: usize) -> Option<u32> {
+            Some(1)
+        }
+    }
+
+    fn build(topology: Topology, mask: Option<CellMask>, seed: u64) -> Maze {
+        let options = BuildOptions {
+            topology,
+            mask,
+            algorithm: Algorithm::UniformSpanningTree,
+            seed: Some(seed),
+            ..Default::default()
+        };
+        Maze::build_with_options(7, 6, WallWeights::from_fn(|_| 1), options).unwrap()
+    }
+
+    #[test]
+    fn mazes_are_spanning_trees() {
+        let mut mask = CellMask::new(7, 6);
+        mask.exclude_rect(2, 2, 2, 3);
+        for topology in [Topology::PLANE, Topology::TORUS, Topology::KLEIN] {
+            for mask in [None, Some(mask.clone())] {
+                for seed in 0..10 {
+                    let maze = build(topology, mask.clone(), seed);
+                    let open_cells = mask.as_ref().map_or(42, CellMask::count);
+                    let openings = maze
+                        .edges()
+                        .filter(|&edge| maze.wall(edge) == Some(false))
+                        .count();
+                    assert_eq!(openings, open_cells - 1, "{:?}", topology);
+                    assert!(maze.is_connected());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mazes_are_reproducible_from_a_seed() {
+        for seed in 0..10 {
+            let maze = build(Topology::TORUS, None, seed);
+            assert_eq!(build(Topology::TORUS, None, seed).grid, maze.grid);
+            assert_ne!(build(Topology::TORUS, None, seed + 100).grid, maze.grid);
+        }
+    }
+
+    #[test]
+    fn disconnected_candidates_are_rejected() {
+        let graph = Row(4);
+        let mut edges = vec![true; 3];
+        let mut cells = UnionFind::new(4);
+        let mut rng = StdRng::seed_from_u64(0);
+        let candidates = [true, false, true];
+        let res = carve(
+            &graph,
+            &mut edges,
+            &mut cells,
+            &candidates,
+            &mut rng,
+            &mut |_, _| {},
+        );
+        assert_eq!(res, None);
+        assert_eq!(edges, [true; 3]);
+
+        let candidates = [true; 3];
+        let res = carve(
+            &graph,
+            &mut edges,
+            &mut cells,
+            &candidates,
+            &mut rng,
+            &mut |_, _| {},
+        );
+        assert_eq!(res, Some(()));
+        assert_eq!(edges, [false; 3]);
+    }
+}
@@ -0,0 +1,49 @@
+// Checks of the `maze batch` command line
+
+use std::process::Command;
+
+fn run_batch(args: &[&str]) -> Vec<u8> {
+    let output = Command::new(env!("CARGO_BIN_EXE_maze"))
+        .arg("batch")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output.stdout
+}
+
+#[test]
+fn output_does_not_depend_on_threads() {
+    for format in ["jsonl", "binary"] {
+        let args = [
+            "--sizes",
+            "6x6,9x7",
+            "--seeds",
+            "0..50",
+            "--preset",
+            "uniform,ramp",
+            "--topology",
+            "klein",
+            "--weave",
+            "0.2",
+            "--format",
+            format,
+        ];
+        let one = run_batch(&[&args[..], &["--threads", "1"]].concat());
+        let four = run_batch(&[&args[..], &["--threads", "4"]].concat());
+        assert!(!one.is_empty());
+        assert!(one == four, "{}", format);
+    }
+}
+
+#[test]
+fn json_lines_hold_one_maze_each() {
+    let out = run_batch(&["--sizes", "4x3", "--seeds", "0..5", "--threads", "2"]);
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 5);
+    for (seed, line) in lines.iter().enumerate() {
+        let prefix = format!("{{\"width\":4,\"height\":3,\"seed\":{},", seed);
+        assert!(line.starts_with(&prefix), "{}", line);
+    }
+}
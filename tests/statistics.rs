@@ -0,0 +1,275 @@
+// Statistical checks that the wall weights steer generation. Every check is
+// run on thousands of seeded draws, so results do not change between runs,
+// and would fail by chance for a given set of seeds with a probability of
+// about one in a million.
+
+use std::collections::HashMap;
+
+use maze::fenwick_tree::FenwickTree;
+use maze::maze::{Algorithm, BuildOptions, Edge, GenerationEvent, Maze, WallType, WallWeights};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Chi-square values exceeded with a probability of 1e-6, by degrees of
+// freedom
+const CRITICAL_1: f64 = 23.93;
+const CRITICAL_7: f64 = 40.52;
+const CRITICAL_11: f64 = 48.87;
+const CRITICAL_14: f64 = 54.64;
+
+// Chi-square statistic of observed counts against expected counts
+fn chi_square(observed: &[usize], expected: &[f64]) -> f64 {
+    observed
+        .iter()
+        .zip(expected)
+        .map(|(&o, &e)| (o as f64 - e).powi(2) / e)
+        .sum()
+}
+
+// Chi-square statistic of a 2x2 table of `hits` out of `totals` for two
+// samples
+fn chi_square_2x2(hits: [usize; 2], totals: [usize; 2]) -> f64 {
+    let [a, c] = hits.map(|h| h as f64);
+    let [b, d] = [totals[0] - hits[0], totals[1] - hits[1]].map(|m| m as f64);
+    let n = a + b + c + d;
+    n * (a * d - b * c).powi(2) / ((a + b) * (c + d) * (a + c) * (b + d))
+}
+
+// Number of walls of the given type drawn by the generator, and the total
+// number of walls drawn, over `runs` mazes
+fn count_considered(runs: usize, wall_type: WallType, config: &WallWeights) -> (usize, usize) {
+    let mut hits = 0;
+    let mut total = 0;
+    for seed in 0..runs as u64 {
+        let options = BuildOptions {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        Maze::build_with_events(6, 6, config.clone(), options, |event| {
+            if let GenerationEvent::EdgeConsidered { wall_type: t, .. } = event {
+                total += 1;
+                if t == wall_type {
+                    hits += 1;
+                }
+            }
+        })
+        .unwrap();
+    }
+    (hits, total)
+}
+
+// Number of walls of the given type left in the generated mazes, and the
+// total number of walls left, over `runs` mazes
+fn count_kept(runs: usize, wall_type: WallType, config: &WallWeights) -> (usize, usize) {
+    let mut hits = 0;
+    let mut total = 0;
+    for seed in 0..runs as u64 {
+        let options = BuildOptions {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let maze = Maze::build_with_options(6, 6, config.clone(), options).unwrap();
+        for edge in maze.edges().filter(|&edge| maze.wall(edge) == Some(true)) {
+            total += 1;
+            if maze.classify_edge(edge) == Some(wall_type) {
+                hits += 1;
+            }
+        }
+    }
+    (hits, total)
+}
+
+// Asserts that `wall_type` makes up a larger share of the `hits` out of
+// `totals` in the second sample than in the first
+fn assert_rate_increases(wall_type: WallType, hits: [usize; 2], totals: [usize; 2]) {
+    let base_rate = hits[0] as f64 / totals[0] as f64;
+    let rate = hits[1] as f64 / totals[1] as f64;
+    assert!(
+        rate > base_rate,
+        "{:?}: {} <= {}",
+        wall_type,
+        rate,
+        base_rate
+    );
+    let statistic = chi_square_2x2(hits, totals);
+    assert!(
+        statistic > CRITICAL_1,
+        "{:?}: chi-square {}",
+        wall_type,
+        statistic
+    );
+}
+
+#[test]
+fn fenwick_tree_samples_in_proportion_to_values() {
+    let values = [5, 0, 1, 12, 3, 0, 7, 2, 9, 4];
+    let mut tree = FenwickTree::<u32>::with_len(values.len());
+    for (i, &value) in values.iter().enumerate() {
+        tree.set(i, value).unwrap();
+    }
+
+    let draws = 20_000;
+    let mut counts = vec![0; values.len()];
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..draws {
+        let sum = rng.gen_range(1..=tree.get_final_sum());
+        counts[tree.get_lower(sum).unwrap()] += 1;
+    }
+
+    let total: u32 = values.iter().sum();
+    let (observed, expected): (Vec<usize>, Vec<f64>) = values
+        .iter()
+        .zip(&counts)
+        .filter(|(&value, &count)| {
+            assert!(value > 0 || count == 0, "drew an index without weight");
+            value > 0
+        })
+        .map(|(&value, &count)| (count, draws as f64 * value as f64 / total as f64))
+        .unzip();
+    assert_eq!(observed.len(), 8);
+    let statistic = chi_square(&observed, &expected);
+    assert!(statistic < CRITICAL_7, "chi-square {}", statistic);
+}
+
+#[test]
+fn first_wall_is_uniform_with_equal_weights() {
+    let runs = 6_000;
+    let mut counts: HashMap<Edge, usize> = HashMap::new();
+    for seed in 0..runs as u64 {
+        let options = BuildOptions {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let mut first = None;
+        Maze::build_with_events(3, 3, WallWeights::from_fn(|_| 1), options, |event| {
+            if let GenerationEvent::EdgeConsidered { edge, .. } = event {
+                first.get_or_insert(edge);
+            }
+        })
+        .unwrap();
+        *counts.entry(first.unwrap()).or_default() += 1;
+    }
+
+    // A 3x3 maze has 12 inner walls
+    assert_eq!(counts.len(), 12);
+    let observed: Vec<usize> = counts.into_values().collect();
+    let expected = vec![runs as f64 / 12.0; 12];
+    let statistic = chi_square(&observed, &expected);
+    assert!(statistic < CRITICAL_11, "chi-square {}", statistic);
+}
+
+#[test]
+fn heavy_wall_types_are_drawn_more_often() {
+    let boosted = [
+        (
+            WallType::Type111x011,
+            WallWeights {
+                type_111x011: 1000,
+                ..WallWeights::from_fn(|_| 1)
+            },
+        ),
+        (
+            WallType::Type101x011,
+            WallWeights {
+                type_101x011: 1000,
+                ..WallWeights::from_fn(|_| 1)
+            },
+        ),
+        (
+            WallType::Type011x110,
+            WallWeights {
+                type_011x110: 1000,
+                ..WallWeights::from_fn(|_| 1)
+            },
+        ),
+    ];
+    for (wall_type, config) in boosted {
+        let (base_hits, base_total) =
+            count_considered(500, wall_type, &WallWeights::from_fn(|_| 1));
+        let (hits, total) = count_considered(500, wall_type, &config);
+        assert_rate_increases(wall_type, [base_hits, hits], [base_total, total]);
+    }
+}
+
+#[test]
+fn light_wall_types_are_drawn_less_often() {
+    let suppressed = [
+        (
+            WallType::Type111x111,
+            WallWeights {
+                type_111x111: 1,
+                ..WallWeights::from_fn(|_| 1000)
+            },
+        ),
+        (
+            WallType::Type111x000,
+            WallWeights {
+                type_111x000: 1,
+                ..WallWeights::from_fn(|_| 1000)
+            },
+        ),
+    ];
+    for (wall_type, config) in suppressed {
+        let (base_hits, base_total) =
+            count_considered(500, wall_type, &WallWeights::from_fn(|_| 1000));
+        let (hits, total) = count_considered(500, wall_type, &config);
+        assert_rate_increases(wall_type, [hits, base_hits], [total, base_total]);
+    }
+}
+
+// Weights are the odds of a wall being removed, so the walls left in the
+// maze shift towards the light wall types
+#[test]
+fn light_wall_types_are_kept_more_often() {
+    let suppressed = [
+        WallType::Type111x111,
+        WallType::Type111x100,
+        WallType::Type010x000,
+    ];
+    for wall_type in suppressed {
+        let config = WallWeights::from_fn(|t| if t == wall_type { 1 } else { 1000 });
+        let (base_hits, base_total) = count_kept(500, wall_type, &WallWeights::from_fn(|_| 1000));
+        let (hits, total) = count_kept(500, wall_type, &config);
+        assert_rate_increases(wall_type, [base_hits, hits], [base_total, total]);
+    }
+}
+
+#[test]
+fn heavy_wall_types_are_kept_less_often() {
+    let boosted = [
+        WallType::Type111x011,
+        WallType::Type111x100,
+        WallType::Type010x100,
+    ];
+    for wall_type in boosted {
+        let config = WallWeights::from_fn(|t| if t == wall_type { 1000 } else { 1 });
+        let (base_hits, base_total) = count_kept(500, wall_type, &WallWeights::from_fn(|_| 1));
+        let (hits, total) = count_kept(500, wall_type, &config);
+        assert_rate_increases(wall_type, [hits, base_hits], [total, base_total]);
+    }
+}
+
+#[test]
+fn uniform_spanning_trees_are_equally_likely() {
+    let options = BuildOptions {
+        algorithm: Algorithm::UniformSpanningTree,
+        ..Default::default()
+    };
+    let runs = 7_500;
+    let mut counts: HashMap<Vec<Vec<bool>>, usize> = HashMap::new();
+    for seed in 0..runs as u64 {
+        let options = BuildOptions {
+            seed: Some(seed),
+            ..options.clone()
+        };
+        let maze = Maze::build_with_options(3, 2, WallWeights::from_fn(|_| 1), options).unwrap();
+        *counts.entry(maze.grid).or_default() += 1;
+    }
+
+    // A 3x2 grid has 15 spanning trees
+    assert_eq!(counts.len(), 15);
+    let observed: Vec<usize> = counts.into_values().collect();
+    let expected = vec![runs as f64 / 15.0; 15];
+    let statistic = chi_square(&observed, &expected);
+    assert!(statistic < CRITICAL_14, "chi-square {}", statistic);
+}